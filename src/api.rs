@@ -0,0 +1,95 @@
+//! Mod, modpack and GameBanana logic that can be used without the Slint ui.
+//!
+//! Nothing in here takes or returns `App` or any other generated ui type, so other tools can embed
+//! Rust4Diva's core through these functions without running the window.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::ErrorKind;
+use std::path::Path;
+
+pub use crate::config::DivaConfig;
+pub use crate::gamebanana::{GBSearch, GbMetadata, GbMod, GbModDownload, GbSearchResults};
+pub use crate::modmanagement::{DivaMod, DivaModLoader, ModProvenance};
+pub use crate::modpacks::{ExistingPack, ModPack, ModPackMod};
+
+use crate::{config, gamebanana, modmanagement, modpacks};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Loads rust4diva.toml from the config directory, creating a default one if it doesn't exist yet
+pub async fn load_config() -> Result<DivaConfig> {
+    Ok(config::load_diva_config().await?)
+}
+
+pub async fn save_config(cfg: &DivaConfig) -> Result<()> {
+    Ok(config::write_config(cfg.clone()).await?)
+}
+
+/// Sets the game folder modpacks are stored for, this should be called before any of the pack
+/// functions. The mods folder is read from DivaModLoader's config in it
+pub fn set_diva_dir(dir: &Path) -> Result<()> {
+    modmanagement::use_diva_dir(&dir.display().to_string(), "");
+    Ok(())
+}
+
+/// Loads every mod in the game folder's mods directory without touching the saved priority. The
+/// game folder is also set for the pack functions, see [`set_diva_dir`]
+pub fn load_mods(diva_dir: &Path) -> Result<Vec<DivaMod>> {
+    set_diva_dir(diva_dir)?;
+    let mods_dir = modmanagement::current_mods_dir()
+        .ok_or_else(|| std::io::Error::new(ErrorKind::Other, "Unable to read the mods folder"))?;
+    if !mods_dir.is_dir() {
+        return Err(std::io::Error::new(
+            ErrorKind::NotFound,
+            format!("{} does not exist", mods_dir.display()),
+        )
+        .into());
+    }
+    Ok(modmanagement::load_mods_from_dir(
+        mods_dir.display().to_string(),
+    ))
+}
+
+/// Loads the modpacks of the game folder passed to [`set_diva_dir`], keyed by pack name
+pub async fn load_packs() -> Result<HashMap<String, ModPack>> {
+    let mut packs = modpacks::load_mod_packs().await?;
+    modpacks::load_all_packs(&mut packs);
+    Ok(packs)
+}
+
+/// Saves a modpack, replacing any existing pack with the same name
+pub async fn save_pack(pack: &ModPack) -> Result<()> {
+    Ok(modpacks::save_modpack(pack.clone(), ExistingPack::Overwrite).await?)
+}
+
+pub async fn delete_pack(name: &str) -> Result<()> {
+    Ok(modpacks::delete_modpack(&name.to_owned()).await?)
+}
+
+/// Searches GameBanana for Mega Mix+ mods, `sort` is 0 for relevance, 1 for popularity, 2 for
+/// newest and 3 for recently updated
+pub async fn search_mods(query: &str, page: i32, sort: i32) -> Result<GbSearchResults> {
+    search_mods_paged(query, page, sort, gamebanana::GB_DEFAULT_PER_PAGE).await
+}
+
+/// Same as [`search_mods`] with a different number of results per page, capped to the 50
+/// GameBanana allows
+pub async fn search_mods_paged(
+    query: &str,
+    page: i32,
+    sort: i32,
+    per_page: i32,
+) -> Result<GbSearchResults> {
+    gamebanana::search_gb(query.to_owned(), page, sort, "Mod", per_page, |_| {}).await
+}
+
+/// Fetches a mod's GameBanana page data, including its files
+pub async fn fetch_mod(id: i32) -> Result<GBSearch> {
+    gamebanana::fetch_mod(id).await
+}
+
+/// Fetches a mod's name, files and description
+pub async fn fetch_mod_info(id: i32) -> Result<GbMod> {
+    gamebanana::fetch_mod_info(id).await
+}
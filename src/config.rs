@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::io::ErrorKind;
 use std::path::PathBuf;
@@ -11,15 +12,25 @@ use tokio::fs;
 use tokio::sync::broadcast::Sender;
 
 use crate::diva::{
-    find_diva_folder, get_config_dir_sync, get_diva_folder, get_steam_folder, open_error_window,
+    ask_choice, find_diva_folder, get_config_dir_sync, get_diva_folder, get_portable_dir,
+    get_steam_folder, open_error_window, steam_source, DialogAnswer,
 };
-use crate::modmanagement::{get_mods, load_mods, set_mods_table, DivaModLoader};
-use crate::modpacks::{load_mod_packs, ModPackMod};
+use crate::gamebanana::{
+    clear_image_cache, rescale_search_images, start_queued_downloads, DEFAULT_DOWNLOAD_RETRIES,
+    DEFAULT_MAX_DOWNLOADS, GB_DEFAULT_PER_PAGE, GB_MAX_PER_PAGE,
+};
+use crate::modmanagement::{
+    get_mods, init_mods_dir, is_dml_installed, load_mods, mods_dir_exists, set_mods_table,
+    use_diva_dir, DivaModLoader, ModProvenance,
+};
+use crate::modpacks::{load_mod_packs, set_pack_list, ModPackMod};
+use crate::oneclick::{spawn_status_server, stop_status_server, test_oneclick};
 use crate::slint_generatedApp::App;
-use crate::{DML_CFG, MOD_PACKS};
+use crate::{firstlaunch, DML_CFG, MODS_DIR, MOD_PACKS};
 
 use crate::{
-    diva::get_config_dir, DivaLogic, LangTL, SettingsLogic, SettingsWindow, WindowLogic, R4D_CFG,
+    diva::get_config_dir, DivaLogic, LangTL, SettingsLogic, SettingsWindow, TabSetting,
+    WindowLogic, R4D_CFG,
 };
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -77,6 +88,59 @@ pub struct DivaConfig {
     pub lang: i32,
     #[serde(default)]
     pub use_dirname: bool,
+    #[serde(default = "yes")]
+    pub check_updates: bool,
+    #[serde(default)]
+    pub suggest_dependencies: bool,
+    #[serde(default)]
+    pub provenance: HashMap<String, ModProvenance>,
+    #[serde(default = "medium_thumbs")]
+    pub thumb_size: i32,
+    #[serde(default)]
+    pub pinned_mods: Vec<String>,
+    #[serde(default = "default_tabs")]
+    pub tab_order: Vec<String>,
+    #[serde(default)]
+    pub hidden_tabs: Vec<String>,
+    #[serde(default)]
+    pub status_server: bool,
+    #[serde(default = "default_status_port")]
+    pub status_port: u16,
+    #[serde(default = "default_per_page")]
+    pub search_per_page: i32,
+    #[serde(default)]
+    pub embedded_details: bool,
+    /// unix time each mod was last enabled or applied as part of a pack, keyed by mod name
+    #[serde(default)]
+    pub last_used: HashMap<String, i64>,
+    /// mods folder used in place of the game's own, DMM's folder when it was imported by reference
+    #[serde(default)]
+    pub mods_dir: String,
+    /// column the installed mods list is sorted by, -1 shows the mods in load order
+    #[serde(default)]
+    pub mods_sort_column: i32,
+    #[serde(default = "yes")]
+    pub mods_sort_ascending: bool,
+    #[serde(default = "yes")]
+    pub install_downloads: bool,
+    /// installs the mods inside an archive's single wrapping folder instead of the folder itself
+    #[serde(default = "yes")]
+    pub flatten_archives: bool,
+    /// installs GameBanana files that have no md5 checksum to verify them against
+    #[serde(default = "yes")]
+    pub allow_unverified_downloads: bool,
+    /// how many mod files are downloaded at once, the rest wait in the download queue
+    #[serde(default = "default_max_downloads")]
+    pub max_concurrent_downloads: i32,
+    /// how many times a download that failed because of the network or server is tried again
+    #[serde(default = "default_download_retries")]
+    pub download_retries: i32,
+    /// asks before downloading GameBanana files that contain executables
+    #[serde(default = "yes")]
+    pub warn_executables: bool,
+    /// shows search results GameBanana marks as NSFW
+    #[serde(default)]
+    pub show_nsfw: bool,
 }
 impl DivaConfig {
     pub fn new() -> Self {
@@ -96,6 +160,38 @@ impl DivaConfig {
             scale: 1.0,
             lang: 1,
             use_dirname: false,
+            check_updates: true,
+            suggest_dependencies: false,
+            provenance: HashMap::new(),
+            thumb_size: 1,
+            pinned_mods: vec![],
+            tab_order: default_tabs(),
+            hidden_tabs: vec![],
+            status_server: false,
+            status_port: 8642,
+            search_per_page: GB_DEFAULT_PER_PAGE,
+            embedded_details: false,
+            last_used: HashMap::new(),
+            mods_dir: String::new(),
+            mods_sort_column: 0,
+            mods_sort_ascending: true,
+            install_downloads: true,
+            flatten_archives: true,
+            allow_unverified_downloads: true,
+            max_concurrent_downloads: DEFAULT_MAX_DOWNLOADS as i32,
+            download_retries: DEFAULT_DOWNLOAD_RETRIES,
+            warn_executables: true,
+            show_nsfw: false,
+        }
+    }
+
+    /// The scheme the windows use, following the system leaves it to slint which also picks up when
+    /// the system's theme changes
+    pub fn color_scheme(&self) -> ColorScheme {
+        match (self.use_system_theme, self.dark_mode) {
+            (true, _) => ColorScheme::Unknown,
+            (false, true) => ColorScheme::Dark,
+            (false, false) => ColorScheme::Light,
         }
     }
 }
@@ -132,6 +228,28 @@ impl From<OldDivaConfig> for DivaConfig {
             scale: 1.0,
             lang: 1,
             use_dirname: false,
+            check_updates: true,
+            suggest_dependencies: false,
+            provenance: HashMap::new(),
+            thumb_size: 1,
+            pinned_mods: vec![],
+            tab_order: default_tabs(),
+            hidden_tabs: vec![],
+            status_server: false,
+            status_port: 8642,
+            search_per_page: GB_DEFAULT_PER_PAGE,
+            embedded_details: false,
+            last_used: HashMap::new(),
+            mods_dir: String::new(),
+            mods_sort_column: 0,
+            mods_sort_ascending: true,
+            install_downloads: true,
+            flatten_archives: true,
+            allow_unverified_downloads: true,
+            max_concurrent_downloads: DEFAULT_MAX_DOWNLOADS as i32,
+            download_retries: DEFAULT_DOWNLOAD_RETRIES,
+            warn_executables: true,
+            show_nsfw: false,
         }
     }
 }
@@ -140,6 +258,69 @@ fn yes() -> bool {
     true
 }
 
+fn medium_thumbs() -> i32 {
+    1
+}
+
+fn default_status_port() -> u16 {
+    8642
+}
+
+fn default_per_page() -> i32 {
+    GB_DEFAULT_PER_PAGE
+}
+
+fn default_max_downloads() -> i32 {
+    DEFAULT_MAX_DOWNLOADS as i32
+}
+
+fn default_download_retries() -> i32 {
+    DEFAULT_DOWNLOAD_RETRIES
+}
+
+/// Ids of the main window's tabs in their default order, these are also the `tab:` lang keys
+pub const TABS: [&str; 2] = ["mods", "search"];
+
+fn default_tabs() -> Vec<String> {
+    TABS.iter().map(|t| t.to_string()).collect()
+}
+
+/// Every tab in the user's order, tabs missing from the config are added at the end
+pub fn ordered_tabs(cfg: &DivaConfig) -> Vec<String> {
+    let mut tabs: Vec<String> = cfg
+        .tab_order
+        .iter()
+        .filter(|t| TABS.contains(&t.as_str()))
+        .cloned()
+        .collect();
+    for tab in TABS {
+        if !tabs.iter().any(|t| t == tab) {
+            tabs.push(tab.to_string());
+        }
+    }
+    tabs
+}
+
+pub fn visible_tabs(cfg: &DivaConfig) -> Vec<SharedString> {
+    ordered_tabs(cfg)
+        .into_iter()
+        .filter(|t| !cfg.hidden_tabs.contains(t))
+        .map(|t| t.into())
+        .collect()
+}
+
+/// Shows the visible tabs on the main window, switching away from the current tab if it was hidden
+pub fn set_tabs(ui: &App, cfg: &DivaConfig) {
+    let tabs = visible_tabs(cfg);
+    if !tabs.contains(&ui.get_current_tab()) {
+        if let Some(first) = tabs.first() {
+            ui.set_current_tab(first.clone());
+        }
+    }
+    ui.set_search_tab_visible(tabs.iter().any(|t| t == "search"));
+    ui.set_tab_order(ModelRc::new(VecModel::from(tabs)));
+}
+
 impl OldDivaConfig {
     pub fn new() -> Self {
         Self {
@@ -232,6 +413,22 @@ pub fn write_dml_config(dml: DivaModLoader) -> Result<(), Box<dyn Error + Send +
 
 pub static SETTINGS_OPEN: Mutex<bool> = Mutex::new(false);
 
+/// Puts the config back into its first run state. With `clear_settings` the game folder and every
+/// other setting go back to their defaults, what is known about the installed mods like their load
+/// order, sources and pins is kept. Mods and modpacks are never touched
+fn reset_config(clear_settings: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut cfg = R4D_CFG.try_lock().map_err(|e| e.to_string())?;
+    if clear_settings {
+        let old = std::mem::replace(&mut *cfg, DivaConfig::new());
+        cfg.priority = old.priority;
+        cfg.provenance = old.provenance;
+        cfg.pinned_mods = old.pinned_mods;
+        cfg.last_used = old.last_used;
+    }
+    cfg.first_run = true;
+    write_config_sync(cfg.clone())
+}
+
 pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
     let _ui_diva_dir_handle = diva_ui.as_weak();
     let main_ui_handle = diva_ui.as_weak();
@@ -240,23 +437,24 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
     let dark_tx = dark_tx.clone();
 
     let weak = diva_ui.as_weak();
-    diva_ui.global::<DivaLogic>().on_toggle_dml(move || {
-        let weak = weak.clone();
-        if let Ok(mut dml) = DML_CFG.try_lock() {
-            dml.enabled = !dml.enabled;
-            if let Ok(dml_str) = toml::to_string_pretty(&dml.clone()) {
-                if let Some(diva_dir) = find_diva_folder() {
-                    let mut buf = PathBuf::from(diva_dir);
-                    buf.push("config.toml");
-                    match std::fs::write(buf, dml_str) {
-                        Ok(_) => {
-                            weak.upgrade().unwrap().set_dml_enabled(dml.enabled.clone());
-                        }
-                        Err(e) => open_error_window(e.to_string()),
-                    }
-                }
+    diva_ui.global::<DivaLogic>().on_toggle_dml(move |enabled| {
+        let ui = weak.unwrap();
+        let mut dml = match DML_CFG.try_lock() {
+            Ok(dml) => dml,
+            Err(e) => {
+                ui.set_dml_enabled(!enabled);
+                open_error_window(e.to_string());
+                return;
             }
+        };
+        let mut updated = dml.clone();
+        updated.enabled = enabled;
+        // writes a new config.toml if DivaModLoader doesn't have one yet
+        match write_dml_config(updated.clone()) {
+            Ok(_) => *dml = updated,
+            Err(e) => open_error_window(format!("Unable to update DivaModLoader's config:\n{e}")),
         }
+        ui.set_dml_enabled(dml.enabled);
     });
 
     let weak = diva_ui.as_weak();
@@ -280,6 +478,9 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
                             .invoke_get_localized_string(unlocalized)
                     });
 
+                if cfg!(target_os = "linux") && steam_dir != "Not Set" {
+                    settings.set_steam_source(steam_source(&steam_dir).into());
+                }
                 settings.set_steam_dir(steam_dir.into());
                 settings.set_diva_dir(diva_dir.into());
                 settings.invoke_set_color_scheme(current_scheme);
@@ -301,6 +502,35 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
                     settings.set_f_scale(cfg.scale);
                     settings.set_i_lang(cfg.lang);
                     settings.set_b_dirname(cfg.use_dirname);
+                    settings.set_b_check_updates(cfg.check_updates);
+                    settings.set_b_suggest_deps(cfg.suggest_dependencies);
+                    settings.set_i_thumb_size(cfg.thumb_size);
+                    let tabs: Vec<TabSetting> = ordered_tabs(&cfg)
+                        .into_iter()
+                        .map(|t| TabSetting {
+                            visible: !cfg.hidden_tabs.contains(&t),
+                            id: t.into(),
+                        })
+                        .collect();
+                    settings.set_tabs(ModelRc::new(VecModel::from(tabs)));
+                    settings.set_b_status_server(cfg.status_server);
+                    settings.set_i_per_page(cfg.search_per_page);
+                    settings.set_i_max_downloads(cfg.max_concurrent_downloads);
+                    settings.set_s_mods_dir(cfg.mods_dir.clone().into());
+                    settings.set_i_download_retries(cfg.download_retries);
+                    settings.set_b_embedded_details(cfg.embedded_details);
+                    settings.set_b_install_downloads(cfg.install_downloads);
+                    settings.set_b_flatten_archives(cfg.flatten_archives);
+                    settings.set_b_allow_unverified(cfg.allow_unverified_downloads);
+                    settings.set_b_warn_executables(cfg.warn_executables);
+                    settings.set_b_show_nsfw(cfg.show_nsfw);
+                    if let Ok(dir) = MODS_DIR.try_lock() {
+                        settings.set_current_mods_dir(dir.clone().into());
+                    }
+                    settings.set_portable(get_portable_dir().is_some());
+                    if let Ok(dir) = get_config_dir_sync() {
+                        settings.set_data_dir(dir.display().to_string().into());
+                    }
                 }
 
                 let main_ui = main_close_handle.unwrap();
@@ -399,6 +629,86 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
                         }
                     });
 
+                let weak = settings.as_weak();
+                settings
+                    .global::<SettingsLogic>()
+                    .on_move_tab_up(move |idx| {
+                        let settings = weak.unwrap();
+                        let model = settings.get_tabs();
+                        if let Some(vec) = model.as_any().downcast_ref::<VecModel<TabSetting>>() {
+                            let idx = idx as usize;
+                            if idx > 0 && idx < vec.row_count() {
+                                let tab = vec.remove(idx);
+                                vec.insert(idx - 1, tab);
+                            }
+                        }
+                    });
+
+                settings
+                    .global::<SettingsLogic>()
+                    .on_open_config_folder(|| {
+                        // get_config_dir_sync creates the folder if it doesn't exist yet
+                        match get_config_dir_sync() {
+                            Ok(dir) => {
+                                if let Err(e) = open::that(dir) {
+                                    open_error_window(format!(
+                                        "Unable to open the config folder: \n{e}"
+                                    ));
+                                }
+                            }
+                            Err(e) => open_error_window(e.to_string()),
+                        }
+                    });
+
+                settings.global::<SettingsLogic>().on_test_oneclick(|| {
+                    tokio::spawn(test_oneclick());
+                });
+
+                settings.global::<SettingsLogic>().on_clear_image_cache(|| {
+                    if let Err(e) = clear_image_cache() {
+                        open_error_window(format!("Unable to clear the image cache: {e}"));
+                    }
+                });
+
+                let reset_handle = settings.as_weak();
+                let app_handle = main_ui_handle.clone();
+                settings
+                    .global::<SettingsLogic>()
+                    .on_reset_setup(move || {
+                        let reset_handle = reset_handle.clone();
+                        let app_handle = app_handle.clone();
+                        tokio::spawn(async move {
+                            let answer = ask_choice(
+                                "Reset Setup".to_owned(),
+                                "First time setup will open again. Your other settings can be kept or reset to their defaults as well, mods and modpacks are kept either way.".to_owned(),
+                                Some("Keep Settings".to_owned()),
+                                Some("Clear Settings".to_owned()),
+                            )
+                            .await;
+                            if answer == DialogAnswer::Cancel {
+                                return;
+                            }
+                            if let Err(e) = reset_config(answer == DialogAnswer::Alternate) {
+                                open_error_window(format!("Unable to reset setup: \n{e}"));
+                                return;
+                            }
+                            let _ = reset_handle.upgrade_in_event_loop(move |settings| {
+                                if let Ok(mut open) = SETTINGS_OPEN.try_lock() {
+                                    *open = false;
+                                }
+                                let _ = settings.hide();
+                                let Some(app) = app_handle.upgrade() else {
+                                    return;
+                                };
+                                let _ = slint::spawn_local(async move {
+                                    if let Err(e) = firstlaunch::init(&app).await {
+                                        open_error_window(e.to_string());
+                                    }
+                                });
+                            });
+                        });
+                    });
+
                 let apply_handle = settings.as_weak();
                 let sweak = settings.as_weak();
                 let color_handle = main_ui_handle.clone();
@@ -409,6 +719,10 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
                         let color_handle = color_handle.clone();
                         let apply_handle = apply_handle.clone();
                         let mut lcfg = None;
+                        let mut thumbs_changed = false;
+                        let mut nsfw_changed = false;
+                        let mut mods_dir_changed = false;
+                        let mut diva_dir_changed = false;
                         if let Ok(mut cfg) = R4D_CFG.lock() {
                             let mut dirs = vec![];
                             for dir in settings.diva_dirs.iter() {
@@ -428,10 +742,33 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
                                     return;
                                 }
                             }
+                            // the picked folder is switched to as is, R4D_DIVA_DIR and steam
+                            // aren't asked so it has to be a game folder itself
+                            if cfg.diva_dir != settings.diva_dir.as_str()
+                                && !PathBuf::from(settings.diva_dir.as_str())
+                                    .join("DivaMegaMix.exe")
+                                    .is_file()
+                            {
+                                open_error_window(format!(
+                                    "{} can't be used, DivaMegaMix.exe isn't in it",
+                                    settings.diva_dir
+                                ));
+                                return;
+                            }
+                            let mods_dir = settings.mods_dir.trim().to_string();
+                            if !mods_dir.is_empty()
+                                && !mods_dir_exists(settings.diva_dir.as_str(), &mods_dir)
+                            {
+                                open_error_window(format!(
+                                    "The mods folder {mods_dir} does not exist"
+                                ));
+                                return;
+                            }
                             if PathBuf::from(settings.steam_dir.to_string()).exists() {
                                 cfg.steam_dir = settings.steam_dir.to_string().clone();
                             }
 
+                            diva_dir_changed = cfg.diva_dir != settings.diva_dir.as_str();
                             cfg.diva_dir = settings.diva_dir.to_string();
                             cfg.diva_dirs = dirs;
                             cfg.aft_mode = settings.aft_mode;
@@ -441,9 +778,58 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
                             cfg.scale = settings.scale.clamp(0.1, 10.0);
                             cfg.lang = settings.language;
                             cfg.use_dirname = settings.use_dirname;
+                            cfg.check_updates = settings.check_updates;
+                            cfg.suggest_dependencies = settings.suggest_deps;
+                            cfg.tab_order =
+                                settings.tabs.iter().map(|t| t.id.to_string()).collect();
+                            cfg.hidden_tabs = settings
+                                .tabs
+                                .iter()
+                                .filter(|t| !t.visible)
+                                .map(|t| t.id.to_string())
+                                .collect();
+                            // the mods tab is kept if everything was hidden
+                            if cfg.hidden_tabs.len() >= TABS.len() {
+                                cfg.hidden_tabs.retain(|t| t != "mods");
+                            }
+                            thumbs_changed = cfg.thumb_size != settings.thumb_size;
+                            nsfw_changed = cfg.show_nsfw != settings.show_nsfw;
+                            cfg.thumb_size = settings.thumb_size;
+                            if settings.status_server && !cfg.status_server {
+                                let port = cfg.status_port;
+                                tokio::spawn(async move {
+                                    if let Err(e) = spawn_status_server(port).await {
+                                        open_error_window(format!(
+                                            "Unable to start the download status server: \n{e}"
+                                        ));
+                                    }
+                                });
+                            } else if !settings.status_server && cfg.status_server {
+                                stop_status_server();
+                            }
+                            cfg.status_server = settings.status_server;
+                            cfg.search_per_page = settings.per_page.clamp(1, GB_MAX_PER_PAGE);
+                            cfg.max_concurrent_downloads = settings.max_downloads.max(1);
+                            cfg.download_retries = settings.download_retries.max(0);
+                            cfg.embedded_details = settings.embedded_details;
+                            cfg.install_downloads = settings.install_downloads;
+                            cfg.flatten_archives = settings.flatten_archives;
+                            cfg.allow_unverified_downloads = settings.allow_unverified;
+                            cfg.warn_executables = settings.warn_executables;
+                            cfg.show_nsfw = settings.show_nsfw;
+                            mods_dir_changed = cfg.mods_dir != mods_dir;
+                            cfg.mods_dir = mods_dir;
                             lcfg = Some(cfg.clone());
                         }
                         if let Some(cfg) = lcfg {
+                            // has to be in place before the mods are loaded again below
+                            if diva_dir_changed {
+                                use_diva_dir(&cfg.diva_dir, &cfg.mods_dir);
+                            } else if mods_dir_changed {
+                                init_mods_dir(&cfg.mods_dir);
+                            }
+                            // more slots may have been opened for the queued downloads
+                            start_queued_downloads();
                             tokio::spawn(async move {
                                 let cfg = cfg.clone();
                                 match write_config(cfg.clone()).await {
@@ -451,39 +837,31 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
                                         println!("Config successfully updated");
                                         let _ =
                                             apply_handle.clone().upgrade_in_event_loop(move |ui| {
-                                                if cfg.use_system_theme {
-                                                    ui.invoke_set_color_scheme(
-                                                        ColorScheme::Unknown,
-                                                    );
-                                                } else if cfg.dark_mode {
-                                                    ui.invoke_set_color_scheme(ColorScheme::Dark);
-                                                } else {
-                                                    ui.invoke_set_color_scheme(ColorScheme::Light);
-                                                }
+                                                ui.invoke_set_color_scheme(cfg.color_scheme());
                                                 // ui.invoke_reload_translation();
                                             });
                                         let _ =
                                             color_handle.clone().upgrade_in_event_loop(move |ui| {
                                                 ui.set_b_dirname(cfg.use_dirname);
-                                                if cfg.use_system_theme {
-                                                    ui.invoke_set_color_scheme(
-                                                        ColorScheme::Unknown,
+                                                if diva_dir_changed {
+                                                    ui.set_dml_enabled(
+                                                        is_dml_installed()
+                                                            && DML_CFG
+                                                                .try_lock()
+                                                                .is_ok_and(|dml| dml.enabled),
                                                     );
-                                                } else if cfg.dark_mode {
-                                                    ui.invoke_set_color_scheme(ColorScheme::Dark);
-                                                } else {
-                                                    ui.invoke_set_color_scheme(ColorScheme::Light);
                                                 }
+                                                ui.set_thumb_size(cfg.thumb_size);
+                                                set_tabs(&ui, &cfg);
+                                                if thumbs_changed {
+                                                    rescale_search_images(&ui);
+                                                }
+                                                if nsfw_changed {
+                                                    ui.invoke_rerun_search();
+                                                }
+                                                ui.invoke_set_color_scheme(cfg.color_scheme());
                                             });
-                                        let _ = dark_tx.send(if cfg.use_system_theme {
-                                            ColorScheme::Unknown
-                                        } else {
-                                            if cfg.dark_mode {
-                                                ColorScheme::Dark
-                                            } else {
-                                                ColorScheme::Light
-                                            }
-                                        });
+                                        let _ = dark_tx.send(cfg.color_scheme());
                                         if load_mods().is_ok() {
                                             let _ =
                                                 set_mods_table(&get_mods(), color_handle.clone());
@@ -492,15 +870,8 @@ pub async fn init_ui(diva_ui: &App, dark_tx: Sender<ColorScheme>) {
                                         if let Ok(packs) = load_mod_packs().await {
                                             let _ = color_handle.clone().upgrade_in_event_loop(
                                                 move |ui| {
-                                                    let mut gpacks = MOD_PACKS.lock().unwrap();
-                                                    *gpacks = packs.clone();
-                                                    let ui_packs =
-                                                        VecModel::<SharedString>::default();
-                                                    ui_packs.push("All Mods".into());
-                                                    for (pack, _mods) in gpacks.iter() {
-                                                        ui_packs.push(pack.into());
-                                                    }
-                                                    ui.set_modpacks(ModelRc::new(ui_packs));
+                                                    *MOD_PACKS.lock().unwrap() = packs;
+                                                    set_pack_list(&ui);
                                                     ui.invoke_reload_translation();
                                                 },
                                             );
@@ -2,9 +2,12 @@ use keyvalues_parser::Vdf;
 use slint_interpreter::invoke_from_event_loop;
 use std::io::{Error, ErrorKind};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, Once};
 use std::{env, fs};
 
-use crate::{ErrorMessageWindow, R4D_CFG, DIVA_DIR};
+use crate::modmanagement::GhRelease;
+use crate::util::reqwest_client;
+use crate::{ConfirmDialog, ErrorMessageWindow, DIVA_DIR, R4D_CFG};
 use slint::ComponentHandle;
 
 cfg_if::cfg_if! {
@@ -15,17 +18,24 @@ cfg_if::cfg_if! {
 }
 
 pub const STEAM_FOLDER: &str = ".local/share/Steam";
+/// Where flatpak Steam keeps its data inside the sandbox, newer installs use the first one
+pub const STEAM_FOLDERS_FLATPAK: [&str; 2] = [
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/data/Steam",
+];
 pub const STEAM_FOLDER_MAC: &str = "Library/Application Support/Steam";
 pub const STEAM_LIBRARIES_CONFIG: &str = "config/libraryfolders.vdf";
 pub const MEGA_MIX_APP_ID: &str = "1761390";
 pub const DIVA_MOD_FOLDER_SUFFIX: &str = "steamapps/common/Hatsune Miku Project DIVA Mega Mix Plus";
+pub const R4D_LATEST_RELEASE: &str =
+    "https://api.github.com/repos/R3alCl0ud/Rust4Diva/releases/latest";
 
 pub fn create_tmp_if_not() -> std::io::Result<()> {
     match get_temp_folder() {
         Some(p) => {
             let path = Path::new(&p);
             if !path.exists() {
-                let dir = fs::create_dir(path);
+                let dir = fs::create_dir_all(path);
                 return dir;
             }
             Ok(())
@@ -37,7 +47,27 @@ pub fn create_tmp_if_not() -> std::io::Result<()> {
     }
 }
 
+/// The folder everything is stored in when a `portable.txt` sits next to the executable
+static PORTABLE_DIR: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
+    let exe = env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    if !exe_dir.join(PORTABLE_MARKER).exists() {
+        return None;
+    }
+    Some(exe_dir.join("rust4diva-data"))
+});
+
+pub const PORTABLE_MARKER: &str = "portable.txt";
+
+/// Returns the data folder next to the executable when running in portable mode
+pub fn get_portable_dir() -> Option<PathBuf> {
+    PORTABLE_DIR.clone()
+}
+
 pub fn get_temp_folder() -> Option<String> {
+    if let Some(dir) = get_portable_dir() {
+        return Some(dir.join("tmp").display().to_string());
+    }
     match env::consts::OS {
         "linux" | "macos" => Some("/tmp/rust4diva".to_string()),
         "windows" => {
@@ -68,18 +98,18 @@ pub fn get_steam_folder() -> Option<String> {
     println!("Attempting to find the Steam folder");
     return match env::consts::OS {
         "linux" => {
-            let mut binding = dirs::home_dir().unwrap();
-            binding.push(STEAM_FOLDER);
-            if !binding.exists() {
-                println!("Regular Steam folder not found, searching for flatpak isntead");
-                binding = dirs::home_dir().unwrap();
-                binding.push(".var/app/com.valvesoftware.Steam/data/Steam");
-                if !binding.exists() {
-                    println!("Can't find flatpak steam.");
-                    return None;
+            let home = dirs::home_dir()?;
+            match find_linux_steam_folder(&home) {
+                Some(dir) => {
+                    let dir = dir.display().to_string();
+                    println!("Using {} Steam folder: {dir}", steam_source(&dir));
+                    Some(dir)
+                }
+                None => {
+                    println!("Can't find a native or flatpak Steam folder");
+                    None
                 }
             }
-            Some(binding.display().to_string())
         }
         "macos" => {
             let mut binding = dirs::home_dir().unwrap();
@@ -122,6 +152,29 @@ pub fn get_steam_folder() -> Option<String> {
     };
 }
 
+/// The native or flatpak Steam folder in `home`. A folder with the library list is preferred, an old
+/// native folder can be left behind after switching to flatpak
+fn find_linux_steam_folder(home: &Path) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = [STEAM_FOLDER]
+        .iter()
+        .chain(STEAM_FOLDERS_FLATPAK.iter())
+        .map(|dir| home.join(dir))
+        .collect();
+    candidates
+        .iter()
+        .find(|dir| dir.join(STEAM_LIBRARIES_CONFIG).exists())
+        .or_else(|| candidates.iter().find(|dir| dir.exists()))
+        .cloned()
+}
+
+/// How Steam is installed at this folder, shown next to the detected Steam folder
+pub fn steam_source(steam_dir: &str) -> &'static str {
+    match steam_dir.contains("com.valvesoftware.Steam") {
+        true => "Flatpak",
+        false => "Native",
+    }
+}
+
 pub fn get_diva_folder() -> Option<String> {
     if let Ok(dir) = DIVA_DIR.try_lock() {
         return Some(dir.clone());
@@ -129,7 +182,29 @@ pub fn get_diva_folder() -> Option<String> {
     return find_diva_folder();
 }
 
+/// Points Rust4Diva at a game folder for this run only, it is used before the config and steam
+pub const DIVA_DIR_ENV: &str = "R4D_DIVA_DIR";
+static DIVA_DIR_ENV_WARNING: Once = Once::new();
+
+/// The game folder set through [`DIVA_DIR_ENV`], None if it isn't set or isn't a Mega Mix+ install.
+/// An invalid folder is only warned about once
+pub fn env_diva_folder() -> Option<String> {
+    let dir = env::var(DIVA_DIR_ENV).ok().filter(|d| !d.is_empty())?;
+    if Path::new(&dir).join("DivaMegaMix.exe").exists() {
+        return Some(dir);
+    }
+    DIVA_DIR_ENV_WARNING.call_once(|| {
+        open_error_window(format!(
+            "{DIVA_DIR_ENV} is set to {dir} but DivaMegaMix.exe isn't in it, the game folder from the config or steam is used instead"
+        ));
+    });
+    None
+}
+
 pub fn find_diva_folder() -> Option<String> {
+    if let Some(dir) = env_diva_folder() {
+        return Some(dir);
+    }
     // try retreiving from the config second
     if let Ok(cfg) = R4D_CFG.try_lock() {
         let mut buf = PathBuf::from(cfg.diva_dir.clone());
@@ -145,82 +220,66 @@ pub fn find_diva_folder() -> Option<String> {
     }
 
     match get_steam_folder() {
-        Some(steam_folder) => {
-            let mut path = "".to_owned();
-            let mut lib_path = PathBuf::new();
-            lib_path.push(steam_folder);
-            lib_path.push(STEAM_LIBRARIES_CONFIG);
-            if !lib_path.exists() {
-                return None;
-            }
+        Some(steam_folder) => find_diva_in_steam(Path::new(&steam_folder)),
+        None => None,
+    }
+}
 
-            let binding = fs::read_to_string(lib_path).unwrap();
-            let lf_res = Vdf::parse(binding.as_str());
-            match lf_res {
-                Ok(libraryfolders) => {
-                    let libraries = libraryfolders.value.unwrap_obj();
-                    for library_id in libraries.clone().keys() {
-                        // get the library obj
-                        let library = libraries
-                            .get(library_id)
-                            .unwrap()
-                            .first()
-                            .unwrap()
-                            .clone()
-                            .unwrap_obj();
-                        // prevent a crash in case of malformed libraryfolders.vdf
-                        if !library.contains_key("apps") || !library.contains_key("path") {
-                            continue;
-                        }
-                        // get the list of apps installed to this library
-                        let apps = library
-                            .get("apps")
-                            .unwrap()
-                            .first()
-                            .unwrap()
-                            .clone()
-                            .unwrap_obj();
-                        // self-explanatory
-                        if apps.contains_key(MEGA_MIX_APP_ID) {
-                            // get the path of the library
-                            let path_str =
-                                library.get("path").unwrap().first().unwrap().to_string();
-                            // this is set up for removing the quotes
-                            let mut path_chars = path_str.chars();
-                            // remove the quotes from the value
-                            path_chars.next();
-                            path_chars.next_back();
-                            // concat the strings together properly
-                            let mut buf = PathBuf::new();
-                            buf.push(path_chars.as_str());
-                            let diva = PathBuf::from(DIVA_MOD_FOLDER_SUFFIX);
-                            buf.push(diva.as_os_str());
-                            path = buf
-                                .canonicalize()
-                                .unwrap()
-                                .as_os_str()
-                                .to_str()
-                                .unwrap()
-                                .to_string();
-                            println!("PD MMP Folder: {:?}", path);
-                            break;
-                        }
-                    }
-                    Some(path)
-                }
-                Err(e) => {
-                    eprintln!("{}", e);
-                    None
-                }
-            }
-        }
-        None => {
+/// The libraries listed in Steam's libraryfolders.vdf and whether each one lists Mega Mix+ as
+/// installed, None if the file is missing or can't be parsed
+fn steam_libraries(steam_folder: &Path) -> Option<Vec<(PathBuf, bool)>> {
+    let text = fs::read_to_string(steam_folder.join(STEAM_LIBRARIES_CONFIG)).ok()?;
+    let libraryfolders = match Vdf::parse(text.as_str()) {
+        Ok(libraryfolders) => libraryfolders,
+        Err(e) => {
+            eprintln!("{}", e);
             return None;
         }
+    };
+    let libraries = libraryfolders.value.get_obj()?;
+    let mut found = vec![];
+    for library in libraries.values().filter_map(|l| l.first()?.get_obj()) {
+        // malformed entries are skipped instead of crashing
+        let Some(path) = library.get("path").and_then(|p| p.first()?.get_str()) else {
+            continue;
+        };
+        let has_diva = library
+            .get("apps")
+            .and_then(|a| a.first()?.get_obj())
+            .is_some_and(|apps| apps.contains_key(MEGA_MIX_APP_ID));
+        found.push((PathBuf::from(path), has_diva));
+    }
+    Some(found)
+}
+
+/// Looks through every Steam library for the game folder with DivaMegaMix.exe in it. The apps a
+/// library lists can be out of date, so the ones listing the game are only checked first. If none of
+/// them has the exe, the library that lists the game is used
+fn find_diva_in_steam(steam_folder: &Path) -> Option<String> {
+    let libraries = steam_libraries(steam_folder)?;
+    let mut ordered = libraries.clone();
+    ordered.sort_by_key(|(_, has_diva)| !has_diva);
+    for (library, _) in ordered {
+        let dir = library.join(DIVA_MOD_FOLDER_SUFFIX);
+        if dir.join("DivaMegaMix.exe").exists() {
+            let dir = dir.canonicalize().unwrap_or(dir);
+            println!("PD MMP Folder: {:?}", dir);
+            return Some(dir.display().to_string());
+        }
     }
+    let path = libraries
+        .iter()
+        .find(|(_, has_diva)| *has_diva)
+        .map(|(library, _)| library.join(DIVA_MOD_FOLDER_SUFFIX).display().to_string())
+        .unwrap_or_default();
+    Some(path)
 }
 
 pub fn get_config_dir() -> std::io::Result<PathBuf> {
+    if let Some(dir) = get_portable_dir() {
+        fs::create_dir_all(&dir)?;
+        return Ok(dir);
+    }
     match dirs::config_dir() {
         Some(mut buf) => {
             buf.push("rust4diva");
@@ -237,6 +296,10 @@ pub fn get_config_dir() -> std::io::Result<PathBuf> {
 }
 
 pub fn get_config_dir_sync() -> std::io::Result<PathBuf> {
+    if let Some(dir) = get_portable_dir() {
+        fs::create_dir_all(&dir)?;
+        return Ok(dir);
+    }
     match dirs::config_dir() {
         Some(mut buf) => {
             buf.push("rust4diva");
@@ -251,6 +314,61 @@ pub fn get_config_dir_sync() -> std::io::Result<PathBuf> {
         )),
     }
 }
+const CRASH_LOG: &str = "crash.log";
+
+/// Writes panics to a crash log in the config dir, without a console on windows there is no other
+/// trace of them. Nothing is sent anywhere, the log is only offered to the user on the next launch
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let thread = std::thread::current();
+        let report = format!(
+            "Rust4Diva {} crashed at {}\nOS: {} {}\nThread: {}\n{}\n\nBacktrace:\n{}\n",
+            get_rust4diva_version(),
+            chrono::Local::now().to_rfc3339(),
+            env::consts::OS,
+            env::consts::ARCH,
+            thread.name().unwrap_or("unnamed"),
+            info,
+            std::backtrace::Backtrace::force_capture()
+        );
+        if let Ok(mut path) = get_config_dir_sync() {
+            path.push(CRASH_LOG);
+            let _ = fs::write(path, report);
+        }
+        default_hook(info);
+    }));
+}
+
+/// Offers to open the crash log left by the last run, the log is renamed afterwards so it is only
+/// offered once
+pub async fn check_for_crash_log() {
+    let Ok(dir) = get_config_dir() else {
+        return;
+    };
+    let log = dir.join(CRASH_LOG);
+    if !log.exists() {
+        return;
+    }
+    let old = dir.join(format!(
+        "crash-{}.log",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    if let Err(e) = fs::rename(&log, &old) {
+        eprintln!("Unable to move crash log: {e}");
+        return;
+    }
+    let msg = format!(
+        "Rust4Diva crashed the last time it was running, the details were saved to:\n{}\n\nOpen the crash log? Attaching it to a bug report helps a lot.",
+        old.display()
+    );
+    if ask_confirmation("Rust4Diva Crashed".to_owned(), msg).await {
+        if let Err(e) = open::that(old) {
+            eprintln!("{e}");
+        }
+    }
+}
+
 #[cfg(not(debug_assertions))]
 pub static MIKU_ART: &'static str = r#"
 　　🟦　　　　　　　　　　　　　　🟦　　　　　　　　　🟦🟦🟦　　　　　　　　　　　🟦　　　　
@@ -282,6 +400,216 @@ pub fn open_error_window(message: String) {
     });
 }
 
+/// Opens a confirmation dialog and waits for the user to answer it, closing the dialog counts as a no
+pub async fn ask_confirmation(title: String, message: String) -> bool {
+    ask_choice(title, message, None, None).await == DialogAnswer::Confirm
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DialogAnswer {
+    Confirm,
+    Alternate,
+    Cancel,
+}
+
+/// Same as [`ask_confirmation`] but with custom button text, the alternate button is only shown
+/// when `alt_text` is set
+pub async fn ask_choice(
+    title: String,
+    message: String,
+    confirm_text: Option<String>,
+    alt_text: Option<String>,
+) -> DialogAnswer {
+    type Answer = Arc<Mutex<Option<tokio::sync::oneshot::Sender<DialogAnswer>>>>;
+    fn answer(tx: &Answer, choice: DialogAnswer) {
+        if let Some(tx) = tx.lock().ok().and_then(|mut tx| tx.take()) {
+            let _ = tx.send(choice);
+        }
+    }
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<DialogAnswer>();
+    let tx: Answer = Arc::new(Mutex::new(Some(tx)));
+    let res = invoke_from_event_loop(move || match ConfirmDialog::new() {
+        Ok(dialog) => {
+            if let Ok(cfg) = R4D_CFG.try_lock() {
+                dialog.invoke_set_color_scheme(cfg.color_scheme());
+            }
+            dialog.set_title_text(title.into());
+            dialog.set_msg(message.into());
+            if let Some(text) = confirm_text {
+                dialog.set_confirm_text(text.into());
+            }
+            if let Some(text) = alt_text {
+                dialog.set_alt_text(text.into());
+            }
+
+            let weak = dialog.as_weak();
+            let confirm_tx = tx.clone();
+            dialog.on_confirm(move || {
+                answer(&confirm_tx, DialogAnswer::Confirm);
+                weak.unwrap().hide().unwrap();
+            });
+            let weak = dialog.as_weak();
+            let alt_tx = tx.clone();
+            dialog.on_alt(move || {
+                answer(&alt_tx, DialogAnswer::Alternate);
+                weak.unwrap().hide().unwrap();
+            });
+            let weak = dialog.as_weak();
+            let close_tx = tx.clone();
+            dialog.on_close(move || {
+                answer(&close_tx, DialogAnswer::Cancel);
+                weak.unwrap().hide().unwrap();
+            });
+            let close_tx = tx.clone();
+            dialog.window().on_close_requested(move || {
+                answer(&close_tx, DialogAnswer::Cancel);
+                slint::CloseRequestResponse::HideWindow
+            });
+            dialog.show().unwrap();
+        }
+        Err(e) => {
+            eprintln!("{e}");
+        }
+    });
+    if let Err(e) = res {
+        eprintln!("{e}");
+        return DialogAnswer::Cancel;
+    }
+    rx.await.unwrap_or(DialogAnswer::Cancel)
+}
+
 pub fn get_rust4diva_version() -> String {
     format!("{}{}", env!("CARGO_PKG_VERSION"), env!("GIT_HASH"))
 }
+
+/// Checks GitHub for a newer release of Rust4Diva, returns `None` if we are already up to date
+pub async fn check_for_r4d_update(
+) -> Result<Option<GhRelease>, Box<dyn std::error::Error + Send + Sync>> {
+    let text = reqwest_client()
+        .get(R4D_LATEST_RELEASE)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let release = sonic_rs::from_str::<GhRelease>(&text)?;
+    if is_newer_version(&release.tag_name, env!("CARGO_PKG_VERSION")) {
+        return Ok(Some(release));
+    }
+    Ok(None)
+}
+
+/// Compares two dotted version strings, a leading `v` and any `-suffix` are ignored and missing
+/// parts count as 0 so "1.2" and "1.2.0" are the same version
+pub fn is_newer_version(latest: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u32> {
+        version
+            .trim_start_matches('v')
+            .split(|c| c == '.' || c == '-')
+            .map_while(|part| part.parse::<u32>().ok())
+            .collect()
+    };
+    let (mut latest, mut current) = (parse(latest), parse(current));
+    let len = latest.len().max(current.len());
+    latest.resize(len, 0);
+    current.resize(len, 0);
+    latest > current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_dir;
+
+    #[test]
+    fn newer_versions() {
+        assert!(is_newer_version("1.3.0", "1.2.9"));
+        assert!(is_newer_version("2.0", "1.10.1"));
+        assert!(is_newer_version("1.10", "1.9"));
+        assert!(!is_newer_version("1.2.0", "1.2.0"));
+        assert!(!is_newer_version("1.2.0", "1.3.0"));
+    }
+
+    #[test]
+    fn version_prefix_and_suffix_are_ignored() {
+        assert!(!is_newer_version("v1.2.0", "1.2.0"));
+        assert!(is_newer_version("v1.2.1", "1.2.0"));
+        assert!(!is_newer_version("1.2.0-beta", "1.2.0"));
+        assert!(is_newer_version("1.3.0-rc1", "v1.2.0"));
+    }
+
+    #[test]
+    fn versions_of_different_lengths() {
+        assert!(!is_newer_version("v1.2.0", "1.2"));
+        assert!(!is_newer_version("1.2", "1.2.0"));
+        assert!(is_newer_version("1.2.1", "1.2"));
+        assert!(!is_newer_version("1.2", "1.2.1"));
+    }
+
+    /// Creates a Steam folder in `home` with a library list pointing at `library`
+    fn steam_with_library(home: &Path, steam: &str, library: &Path) -> PathBuf {
+        let steam = home.join(steam);
+        fs::create_dir_all(steam.join("config")).unwrap();
+        let vdf = format!(
+            "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t\t\"apps\"\n\t\t{{\n\t\t\t\"{MEGA_MIX_APP_ID}\"\t\t\"0\"\n\t\t}}\n\t}}\n}}\n",
+            library.display()
+        );
+        fs::write(steam.join(STEAM_LIBRARIES_CONFIG), vdf).unwrap();
+        steam
+    }
+
+    #[test]
+    fn flatpak_steam_is_found() {
+        let home = test_dir("steam-flatpak");
+        let steam = steam_with_library(&home, STEAM_FOLDERS_FLATPAK[0], &home);
+        assert_eq!(find_linux_steam_folder(&home), Some(steam.clone()));
+        assert_eq!(steam_source(&steam.display().to_string()), "Flatpak");
+        let _ = fs::remove_dir_all(home);
+    }
+
+    #[test]
+    fn leftover_native_folder_loses_to_flatpak() {
+        let home = test_dir("steam-leftover");
+        fs::create_dir_all(home.join(STEAM_FOLDER)).unwrap();
+        let steam = steam_with_library(&home, STEAM_FOLDERS_FLATPAK[1], &home);
+        assert_eq!(find_linux_steam_folder(&home), Some(steam));
+        let _ = fs::remove_dir_all(home);
+    }
+
+    #[test]
+    fn native_steam_is_preferred_when_both_have_libraries() {
+        let home = test_dir("steam-both");
+        let native = steam_with_library(&home, STEAM_FOLDER, &home);
+        steam_with_library(&home, STEAM_FOLDERS_FLATPAK[0], &home);
+        assert_eq!(find_linux_steam_folder(&home), Some(native.clone()));
+        assert_eq!(steam_source(&native.display().to_string()), "Native");
+        let _ = fs::remove_dir_all(home);
+    }
+
+    #[test]
+    fn no_steam_folder() {
+        let home = test_dir("steam-none");
+        assert_eq!(find_linux_steam_folder(&home), None);
+        let _ = fs::remove_dir_all(home);
+    }
+
+    #[test]
+    fn game_is_found_through_a_flatpak_library() {
+        let home = test_dir("steam-flatpak-game");
+        let library = home.join("Games/SteamLibrary");
+        let game = library.join(DIVA_MOD_FOLDER_SUFFIX);
+        fs::create_dir_all(&game).unwrap();
+        fs::write(game.join("DivaMegaMix.exe"), "").unwrap();
+        let steam = steam_with_library(&home, STEAM_FOLDERS_FLATPAK[0], &library);
+
+        let libraries = steam_libraries(&steam).unwrap();
+        assert_eq!(libraries, [(library.clone(), true)]);
+        let found = find_diva_in_steam(&steam).unwrap();
+        assert_eq!(
+            PathBuf::from(found),
+            game.canonicalize().unwrap_or(game.clone())
+        );
+        let _ = fs::remove_dir_all(home);
+    }
+}
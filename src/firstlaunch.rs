@@ -1,19 +1,22 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
 use std::sync::{LazyLock, Mutex};
 
 use filenamify::filenamify;
-use futures_util::future::try_join_all;
 
 use crate::config::write_config_sync;
-use crate::diva::{get_diva_folder, open_error_window};
-use crate::modpacks::{self, ModPack, ModPackMod};
+use crate::diva::{
+    ask_choice, ask_confirmation, env_diva_folder, get_diva_folder, open_error_window, DialogAnswer,
+};
+use crate::modmanagement::{count_mod_folders, is_dml_installed_at, use_mods_dir};
+use crate::modpacks::{self, ExistingPack, ModPack, ModPackMod};
 use crate::slint_generatedApp::App;
-use crate::{FirstSetup, Loadout, SetupLogic, R4D_CFG};
+use crate::util::resolve_path_casing;
+use crate::{FirstSetup, Loadout, SetupLogic, MODS_DIR, R4D_CFG};
 use rfd::AsyncFileDialog;
 use serde::{Deserialize, Serialize};
-use slint::private_unstable_api::re_exports::ColorScheme;
 use slint::{Model, ModelRc, VecModel};
 use slint_interpreter::ComponentHandle;
 
@@ -41,6 +44,29 @@ pub struct DmmPDMMConfig {
     #[serde(rename(serialize = "Loadouts", deserialize = "Loadouts"), default)]
     pub loadouts: HashMap<String, Vec<DmmLoadoutMod>>,
 }
+impl DmmPDMMConfig {
+    /// Finds the Project Diva folder, DMM's game path is used when it is valid,
+    /// otherwise it falls back to the parent of the mods folder
+    pub fn diva_dir(&self) -> Option<PathBuf> {
+        if let Some(game_path) = &self.game_path {
+            let mut buf = PathBuf::from(game_path);
+            // DMM stores the path to the executable, but accept the folder too
+            if buf.is_file() {
+                buf.pop();
+            }
+            if buf.join("DivaMegaMix.exe").exists() {
+                return Some(buf);
+            }
+        }
+        let mut buf = PathBuf::from(self.mods_folder.clone()?);
+        buf.pop();
+        match buf.exists() {
+            true => Some(buf),
+            false => None,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DmmLoadoutMod {
     pub name: String,
@@ -50,7 +76,6 @@ pub struct DmmLoadoutMod {
 impl DmmLoadoutMod {
     pub fn to_packmod(self: &Self, mut mods_dir: PathBuf) -> ModPackMod {
         // let mut buf = PathBuf::from(mods_dir.clone());
-        mods_dir.push("mods");
         mods_dir.push(self.name.clone());
         mods_dir.push("config.toml");
         ModPackMod {
@@ -62,18 +87,79 @@ impl DmmLoadoutMod {
 }
 
 pub static DMM_CFG: LazyLock<Mutex<Option<DmmConfig>>> = LazyLock::new(|| Mutex::new(None));
+/// DMM's mods folder when the user chose to manage it in place instead of the game's mods folder
+static DMM_MODS_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
 
-pub async fn init(_diva_ui: &App) -> Result<(), slint::PlatformError> {
+/// Offers to use DMM's mods folder where it is, this is only asked when it isn't already the game's
+/// mods folder and it has mods in it
+async fn ask_use_dmm_mods(pdmm: &DmmPDMMConfig) -> Option<PathBuf> {
+    let mods_dir = PathBuf::from(pdmm.mods_folder.clone()?);
+    let mods_dir = resolve_path_casing(&mods_dir).unwrap_or(mods_dir);
+    if !mods_dir.is_dir() {
+        eprintln!("DMM's mods folder {} does not exist", mods_dir.display());
+        return None;
+    }
+    if let Some(diva_dir) = pdmm.diva_dir() {
+        let canonical = |p: &Path| p.canonicalize().ok();
+        if canonical(&mods_dir) == canonical(&diva_dir.join("mods")) {
+            return None;
+        }
+    }
+    let count = count_mod_folders(&mods_dir);
+    if count == 0 {
+        return None;
+    }
+    let msg = format!(
+        "DMM keeps its {count} mods in {}.\n\nRust4Diva can manage that folder where it is instead of the game's mods folder. \
+        DMM and Rust4Diva will then both change the same mods, so avoid having both open at once, \
+        and anything one of them enables, updates or deletes will also change for the other.",
+        mods_dir.display()
+    );
+    match ask_choice(
+        "Use DMM's Mods Folder".to_owned(),
+        msg,
+        Some("Use in Place".to_owned()),
+        Some("Use the Game's Folder".to_owned()),
+    )
+    .await
+    {
+        DialogAnswer::Confirm => Some(mods_dir),
+        _ => None,
+    }
+}
+
+/// Reads DivaModManager's Config.json from the given folder.
+///
+/// A folder without a Config.json gives a `NotFound` error, while one that can't be parsed gives `InvalidData`
+pub fn load_dmm_config(dmm_dir: &Path) -> std::io::Result<DmmConfig> {
+    let mut buf = PathBuf::from(dmm_dir);
+    buf.push("Config.json");
+    if !buf.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "{} is not a DivaModManager folder, it does not contain a Config.json",
+                dmm_dir.display()
+            ),
+        ));
+    }
+    let cfgstr = fs::read_to_string(&buf)?;
+    match sonic_rs::from_str::<DmmConfig>(cfgstr.as_str()) {
+        Ok(cfg) => Ok(cfg),
+        Err(e) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("DivaModManager's Config.json could not be read, it may be corrupt:\n{e}"),
+        )),
+    }
+}
+
+pub async fn init(diva_ui: &App) -> Result<(), slint::PlatformError> {
     let diva_dir = get_diva_folder();
     if let Ok(cfg) = R4D_CFG.lock() {
-        if cfg.first_run {
+        // a game folder from the environment is only for this run, setup is left for a later one
+        if cfg.first_run && env_diva_folder().is_none() {
             let setup = FirstSetup::new()?;
-            if cfg.dark_mode {
-                setup.invoke_set_color_scheme(ColorScheme::Dark);
-            }
-            if !cfg.dark_mode {
-                setup.invoke_set_color_scheme(ColorScheme::Light);
-            }
+            setup.invoke_set_color_scheme(cfg.color_scheme());
             if let Some(diva_dir) = diva_dir {
                 setup.set_diva_dir(diva_dir.into());
             }
@@ -82,56 +168,49 @@ pub async fn init(_diva_ui: &App) -> Result<(), slint::PlatformError> {
                 let import_handle = import_handle.clone();
                 let picker = AsyncFileDialog::new();
                 tokio::spawn(async move {
-                    if let Some(dmm_dir) = picker.pick_folder().await {
-                        let mut buf = PathBuf::from(dmm_dir.path());
-                        buf.push("Config.json");
-                        if buf.exists() {
-                            if let Ok(cfgstr) = fs::read_to_string(buf) {
-                                match sonic_rs::from_str::<DmmConfig>(cfgstr.as_str()) {
-                                    Ok(cfg) => {
-                                        if let Ok(mut dmmcfg) = DMM_CFG.try_lock() {
-                                            *dmmcfg = Some(cfg.clone());
-                                        }
-                                        if let Some(pdmm) =
-                                            cfg.configs.get(&"Project DIVA Mega Mix+".to_string())
-                                        {
-                                            if let Some(mods_dir) = pdmm.mods_folder.clone() {
-                                                println!("{}", mods_dir);
-                                                let mut mbuf = PathBuf::from(mods_dir);
-                                                mbuf.pop();
-                                                if mbuf.exists() {
-                                                    let _ = import_handle.upgrade_in_event_loop(
-                                                        move |ui| {
-                                                            ui.set_diva_dir(
-                                                                mbuf.display().to_string().into(),
-                                                            );
-                                                        },
-                                                    );
-                                                }
-                                            }
-                                            let mut loadouts: Vec<Loadout> = Default::default();
-                                            for (loadout, _mods) in pdmm.loadouts.iter() {
-                                                println!("Loadout found: {}", loadout);
-                                                loadouts.push(Loadout {
-                                                    name: filenamify(loadout.clone()).into(),
-                                                    import: true,
-                                                });
-                                            }
-                                            loadouts.sort_by_key(|l| l.name.to_string());
-                                            let _ =
-                                                import_handle.upgrade_in_event_loop(move |ui| {
-                                                    ui.set_loadouts(ModelRc::new(VecModel::from(
-                                                        loadouts,
-                                                    )));
-                                                });
-                                        }
-                                    }
-                                    Err(e) => {
-                                        open_error_window(e.to_string());
-                                    }
+                    let mut picker = picker;
+                    let cfg = loop {
+                        let Some(dmm_dir) = picker.pick_folder().await else {
+                            return;
+                        };
+                        match load_dmm_config(dmm_dir.path()) {
+                            Ok(cfg) => break cfg,
+                            Err(e) => {
+                                let msg =
+                                    format!("{e}\n\nWould you like to pick a different folder?");
+                                if !ask_confirmation("Unable to Import DMM".to_owned(), msg).await {
+                                    return;
                                 }
+                                picker = AsyncFileDialog::new().set_directory(dmm_dir.path());
                             }
                         }
+                    };
+                    if let Ok(mut dmmcfg) = DMM_CFG.try_lock() {
+                        *dmmcfg = Some(cfg.clone());
+                    }
+                    if let Some(pdmm) = cfg.configs.get(&"Project DIVA Mega Mix+".to_string()) {
+                        let mods_dir = ask_use_dmm_mods(pdmm).await;
+                        if let Ok(mut dir) = DMM_MODS_DIR.lock() {
+                            *dir = mods_dir;
+                        }
+                        if let Some(diva_dir) = pdmm.diva_dir() {
+                            println!("{}", diva_dir.display());
+                            let _ = import_handle.upgrade_in_event_loop(move |ui| {
+                                ui.set_diva_dir(diva_dir.display().to_string().into());
+                            });
+                        }
+                        let mut loadouts: Vec<Loadout> = Default::default();
+                        for (loadout, _mods) in pdmm.loadouts.iter() {
+                            println!("Loadout found: {}", loadout);
+                            loadouts.push(Loadout {
+                                name: filenamify(loadout.clone()).into(),
+                                import: true,
+                            });
+                        }
+                        loadouts.sort_by_key(|l| l.name.to_string());
+                        let _ = import_handle.upgrade_in_event_loop(move |ui| {
+                            ui.set_loadouts(ModelRc::new(VecModel::from(loadouts)));
+                        });
                     }
                 });
             });
@@ -165,6 +244,7 @@ pub async fn init(_diva_ui: &App) -> Result<(), slint::PlatformError> {
                 });
 
             let apply_handle = setup.as_weak();
+            let app_handle = diva_ui.as_weak();
             setup.global::<SetupLogic>().on_apply(move || {
                 let ui = apply_handle.upgrade().unwrap();
                 let mut diva_buf = PathBuf::from(ui.get_diva_dir().to_string());
@@ -187,77 +267,190 @@ pub async fn init(_diva_ui: &App) -> Result<(), slint::PlatformError> {
                 let dark_mode = ui.get_dark_mode();
                 println!("Dark Mode: {}", dark_mode);
                 println!("PDMM+: {}", diva_buf.display());
-                {
-                    let mut cfg = match R4D_CFG.try_lock() {
-                        Ok(cfg) => cfg,
-                        Err(_) => {
-                            open_error_window("Unable to lock config".to_string());
-                            return;
-                        }
-                    };
-                    cfg.dark_mode = dark_mode;
-                    cfg.diva_dir = diva_buf.display().to_string();
-                    cfg.diva_dirs = vec![cfg.diva_dir.clone()];
-                    cfg.first_run = false;
-                    let cfg = cfg.clone();
-                    match write_config_sync(cfg) {
-                        Ok(_) => ui.hide().unwrap(),
-                        Err(e) => open_error_window(e.to_string()),
+                let summary = setup_summary(&ui, &diva_buf, dark_mode);
+                let finish_handle = apply_handle.clone();
+                let app_handle = app_handle.clone();
+                tokio::spawn(async move {
+                    let answer = ask_choice(
+                        "Finish Setup".to_owned(),
+                        summary,
+                        Some("Finish".to_owned()),
+                        None,
+                    )
+                    .await;
+                    if answer != DialogAnswer::Confirm {
+                        return;
                     }
-                }
-                if let Ok(dmm_cfg_opt) = DMM_CFG.try_lock() {
-                    let mut loadouts: Vec<ModPack> = Vec::new();
-                    if let Some(dmm_cfg) = dmm_cfg_opt.as_ref() {
-                        if let Some(config) =
-                            dmm_cfg.configs.get(&"Project DIVA Mega Mix+".to_string())
-                        {
-                            match ui
-                                .get_loadouts()
-                                .as_any()
-                                .downcast_ref::<VecModel<Loadout>>()
-                            {
-                                Some(loadouts_mod) => {
-                                    for loadout in loadouts_mod.iter() {
-                                        if !loadout.import {
-                                            continue;
-                                        }
+                    let _ = finish_handle.upgrade_in_event_loop(move |ui| {
+                        finish_setup(&ui, diva_buf, dark_mode, app_handle);
+                    });
+                });
+            });
+            setup.show()?;
+        }
+    }
 
-                                        let mut pack =
-                                            ModPack::new(filenamify(loadout.name.to_string()));
+    Ok(())
+}
 
-                                        println!(
-                                            r#"Converting Loadout: "{}" to modpack"#,
-                                            pack.name
-                                        );
-                                        for module in
-                                            config.loadouts.get(&loadout.name.to_string()).unwrap()
-                                        {
-                                            println!("{}", module.name);
-                                            pack.mods.push(module.to_packmod(diva_buf.clone()))
-                                        }
-                                        loadouts.push(pack.clone());
-                                    }
-                                }
-                                None => {}
-                            }
-                        }
+/// Lists what setup is about to save so it can be checked before anything is written
+fn setup_summary(ui: &FirstSetup, diva_buf: &PathBuf, dark_mode: bool) -> String {
+    let dml = match is_dml_installed_at(&diva_buf.display().to_string()) {
+        true => "Installed",
+        false => "Not installed, you will be asked to install it",
+    };
+    let loadouts = ui.get_loadouts().iter().filter(|l| l.import).count();
+    // the switch only picks the theme used when not following the system
+    let system = R4D_CFG.try_lock().is_ok_and(|cfg| cfg.use_system_theme);
+    let theme = match (system, dark_mode) {
+        (true, _) => "System",
+        (false, true) => "Dark",
+        (false, false) => "Light",
+    };
+    let mut summary = format!(
+        "Project Diva: {}\nDivaModLoader: {dml}\nLoadouts to import: {loadouts}\nTheme: {theme}",
+        diva_buf.display()
+    );
+    if let Ok(dir) = DMM_MODS_DIR.lock() {
+        if let Some(dir) = dir.as_ref() {
+            summary += &format!("\nMods folder: {} (shared with DMM)", dir.display());
+        }
+    }
+    summary
+}
 
-                        tokio::spawn(async move {
-                            let mut handles = vec![];
-                            for pack in loadouts {
-                                handles.push(modpacks::save_modpack(pack));
+/// Saves the setup choices and converts the picked DMM loadouts, only called once the summary was confirmed
+fn finish_setup(ui: &FirstSetup, diva_buf: PathBuf, dark_mode: bool, app: slint::Weak<App>) {
+    {
+        let mut cfg = match R4D_CFG.try_lock() {
+            Ok(cfg) => cfg,
+            Err(_) => {
+                open_error_window("Unable to lock config".to_string());
+                return;
+            }
+        };
+        cfg.dark_mode = dark_mode;
+        cfg.diva_dir = diva_buf.display().to_string();
+        cfg.diva_dirs = vec![cfg.diva_dir.clone()];
+        if let Ok(dir) = DMM_MODS_DIR.lock() {
+            if let Some(dir) = dir.as_ref() {
+                cfg.mods_dir = dir.display().to_string();
+            }
+        }
+        cfg.first_run = false;
+        let cfg = cfg.clone();
+        match write_config_sync(cfg.clone()) {
+            Ok(_) => ui.hide().unwrap(),
+            Err(e) => open_error_window(e.to_string()),
+        }
+        if !cfg.mods_dir.is_empty() {
+            use_mods_dir(&cfg.mods_dir);
+        }
+    }
+    let mods_dir = diva_buf.join(
+        MODS_DIR
+            .lock()
+            .map(|d| d.clone())
+            .unwrap_or("mods".to_owned()),
+    );
+    if let Ok(dmm_cfg_opt) = DMM_CFG.try_lock() {
+        let mut loadouts: Vec<ModPack> = Vec::new();
+        if let Some(dmm_cfg) = dmm_cfg_opt.as_ref() {
+            if let Some(config) = dmm_cfg.configs.get(&"Project DIVA Mega Mix+".to_string()) {
+                match ui
+                    .get_loadouts()
+                    .as_any()
+                    .downcast_ref::<VecModel<Loadout>>()
+                {
+                    Some(loadouts_mod) => {
+                        for loadout in loadouts_mod.iter() {
+                            if !loadout.import {
+                                continue;
                             }
-                            match try_join_all(handles).await {
-                                Ok(_) => {}
-                                Err(e) => open_error_window(e.to_string()),
+
+                            let mut pack = ModPack::new(filenamify(loadout.name.to_string()));
+
+                            println!(r#"Converting Loadout: "{}" to modpack"#, pack.name);
+                            for module in config.loadouts.get(&loadout.name.to_string()).unwrap() {
+                                println!("{}", module.name);
+                                pack.mods.push(module.to_packmod(mods_dir.clone()))
                             }
-                        });
+                            loadouts.push(pack.clone());
+                        }
+                    }
+                    None => {}
+                }
+            }
+
+            tokio::spawn(async move {
+                let progress = move |done: usize, total: usize| {
+                    let _ = app.upgrade_in_event_loop(move |ui| {
+                        ui.set_packs_saved(done as i32);
+                        ui.set_packs_total(if done >= total { 0 } else { total as i32 });
+                    });
+                };
+                let existing: Vec<String> = loadouts
+                    .iter()
+                    .filter(|p| modpacks::pack_file_exists(&p.name))
+                    .map(|p| p.name.clone())
+                    .collect();
+                if !existing.is_empty() {
+                    let msg = format!(
+                        "These modpacks already exist and would be replaced by the imported loadouts:\n{}\n\nReplace them?",
+                        existing.join("\n")
+                    );
+                    if !ask_confirmation("Replace Modpacks".to_owned(), msg).await {
+                        loadouts.retain(|p| !existing.contains(&p.name));
                     }
                 }
+                progress(0, loadouts.len());
+                if let Err(e) =
+                    modpacks::save_modpacks(loadouts, ExistingPack::Overwrite, progress).await
+                {
+                    open_error_window(e.to_string());
+                }
             });
-            setup.show()?;
         }
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_dir;
+
+    #[test]
+    fn dmm_config_missing_is_not_found() {
+        let dir = test_dir("dmm-missing");
+        let err = load_dmm_config(&dir).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().contains("not a DivaModManager folder"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn dmm_config_malformed_is_invalid_data() {
+        let dir = test_dir("dmm-malformed");
+        fs::write(dir.join("Config.json"), "{\"CurrentGame\": \"Project DIVA").unwrap();
+        let err = load_dmm_config(&dir).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("may be corrupt"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn dmm_config_valid_loads() {
+        let dir = test_dir("dmm-valid");
+        fs::write(
+            dir.join("Config.json"),
+            r#"{"CurrentGame": "Project DIVA Mega Mix+", "Configs": {"Project DIVA Mega Mix+": {"ModsFolder": "C:\\mods", "Loadouts": {}}}}"#,
+        )
+        .unwrap();
+        let cfg = load_dmm_config(&dir).unwrap();
+        assert_eq!(cfg.current_game, "Project DIVA Mega Mix+");
+        let game = &cfg.configs["Project DIVA Mega Mix+"];
+        assert_eq!(game.mods_folder.as_deref(), Some("C:\\mods"));
+        assert!(game.launcher.is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
 }
@@ -1,33 +1,126 @@
+use std::cell::RefCell;
+use std::cmp::{max, min, Reverse};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
 
+use base64ct::{Base64UrlUnpadded, Encoding};
 use chrono::DateTime;
+use filenamify::filenamify;
 use futures_util::StreamExt;
+use md5::Md5;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
 
 use slint::private_unstable_api::re_exports::ColorScheme;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, oneshot, Semaphore};
+use tokio::task::AbortHandle;
 use tokio::time::sleep;
 // use slint::Pal
-use crate::diva::{get_temp_folder, open_error_window};
-use crate::modmanagement::{get_mods, load_mods, set_mods_table, unpack_mod_path};
-use crate::util::reqwest_client;
+use crate::diva::{ask_confirmation, get_portable_dir, get_temp_folder, open_error_window};
+use crate::modmanagement::{
+    get_mods, get_provenance, is_extraction_error, load_mods, pinned_mods, record_provenance,
+    set_mods_table, unpack_mod_path, DivaMod, ExistingMod, ModProvenance,
+};
+use crate::modpacks::add_mods_to_pack;
+use crate::oneclick::{finish_oneclick_test, ONECLICK_TEST_PREFIX};
+use crate::util::{open_hyperlink, reqwest_client, walk_files};
 use crate::{
-    App, Download, GameBananaLogic, GbDetailsWindow, GbPreviewData, HyperLink, SlGbSubmitter,
-    R4D_CFG,
+    App, Download, GameBananaLogic, GbDetailsWindow, GbPreviewData, HyperLink, ModpackLogic,
+    ScanBadge, SlGbSubmitter, MOD_PACKS, R4D_CFG,
+};
+use slint::{
+    ComponentHandle, Model, ModelRc, Rgba8Pixel, SharedPixelBuffer, SharedString, VecModel, Weak,
 };
-use slint::{ComponentHandle, Model, ModelRc, Rgba8Pixel, SharedPixelBuffer, VecModel, Weak};
-use tokio::sync::mpsc::{channel, Receiver};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
 
 const GB_DOMAIN: &str = "https://gamebanana.com";
 const GB_DIVA_ID: i32 = 16522;
 const GB_MOD_DATA: &'static str = "apiv11/Mod";
 const GB_MOD_SEARCH: &str = "apiv11/Util/Search/Results";
+const GB_LEGACY_API: &str = "https://api.gamebanana.com";
+const GB_LEGACY_DATA: &str = "Core/Item/Data";
+const LOADOUT_URL_PREFIX: &str = "divamodmanager:loadout:";
+pub const GB_DEFAULT_PER_PAGE: i32 = 30;
+/// the search endpoint won't return more than this many results per page
+pub const GB_MAX_PER_PAGE: i32 = 50;
+/// words in the title of GameBanana's NSFW consent page, lowercase. Only the title is checked since
+/// the menus and scripts of every page on the site can mention NSFW
+const GB_NSFW_GATE_MARKERS: [&str; 2] = ["nsfw", "content warning"];
 #[allow(dead_code)]
 const GB_DIVA_SUBFEED: &str = "apiv11/Game/16522/Subfeed";
+/// the original image bytes are kept so thumbnails can be resized without downloading them again
+const MAX_CACHED_IMAGES: usize = 300;
+/// the images cached on disk are pruned down to this size, least recently used first
+const MAX_IMAGE_CACHE_SIZE: u64 = 200 * 1024 * 1024;
+/// the disk cache is only pruned every so many new images since it has to look at every file
+const IMAGE_CACHE_PRUNE_EVERY: usize = 50;
+static IMAGE_CACHE_WRITES: AtomicUsize = AtomicUsize::new(0);
+/// how many more times a preview image that failed because of the network is requested
+const IMAGE_RETRIES: u64 = 2;
+
+static IMAGE_CACHE: LazyLock<Mutex<HashMap<String, Vec<u8>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+/// mod info responses by mod id with the time they were fetched, see [`cached_mod_info`]
+static MOD_INFO_CACHE: LazyLock<Mutex<HashMap<i32, (Instant, GbMod)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+const MOD_INFO_TTL: Duration = Duration::from_secs(300);
+/// how many of the first search results get their info loaded before they are opened
+const PREFETCH_RESULTS: usize = 5;
+/// limits how many preview images are downloaded at once
+static IMAGE_LOADS: Semaphore = Semaphore::const_new(4);
+/// how many mod files are downloaded at once unless the config says otherwise
+pub const DEFAULT_MAX_DOWNLOADS: usize = 3;
+pub const DEFAULT_DOWNLOAD_RETRIES: i32 = 3;
+static DOWNLOAD_QUEUE: Mutex<DownloadQueue> = Mutex::new(DownloadQueue {
+    running: 0,
+    next_seq: 0,
+    waiting: vec![],
+});
+static VISIBLE_RANGE: Mutex<(i32, i32)> = Mutex::new((0, 0));
+/// how long a new search waits for another one to replace it before it's sent
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(300);
+/// the search that is waiting or running, a newer search cancels it so its results never show up
+static PENDING_SEARCH: Mutex<Option<AbortHandle>> = Mutex::new(None);
+/// index into [`TYPE_FILTERS`] of the submission type that is searched for
+static TYPE_FILTER: AtomicUsize = AtomicUsize::new(1);
+/// the `_sModelName` of each entry in the search tab's type filter, empty searches every type
+const TYPE_FILTERS: [&str; 5] = ["", "Mod", "Sound", "Tool", "Wip"];
+static VISIBLE_IMAGES: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+static LOADING_IMAGES: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+/// more details windows than this close the oldest one when another is opened
+const MAX_DETAILS_WINDOWS: usize = 5;
+static NEXT_DETAILS_KEY: AtomicUsize = AtomicUsize::new(0);
+
+/// An open details window along with the tasks still filling it in
+struct OpenDetails {
+    key: usize,
+    id: i32,
+    window: GbDetailsWindow,
+    tasks: Vec<AbortHandle>,
+}
+
+thread_local! {
+    // windows can only be used on the ui thread
+    static DETAILS_WINDOWS: RefCell<Vec<OpenDetails>> = RefCell::new(vec![]);
+    static PANEL_TASKS: RefCell<Vec<AbortHandle>> = RefCell::new(vec![]);
+}
+
+/// Downloads that are running right now keyed by file id, these are served by the status server
+static ACTIVE_DOWNLOADS: LazyLock<Mutex<HashMap<i32, DownloadStatus>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+/// downloads that can still be cancelled, keyed by file id
+static DOWNLOAD_TASKS: LazyLock<Mutex<HashMap<i32, AbortHandle>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GbModDownload {
@@ -61,8 +154,74 @@ pub struct GbModDownload {
     pub contains_exe: bool,
 }
 
+/// Deserializes a file list, dropping entries that don't parse instead of failing the whole mod
+fn skip_malformed_files<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<GbModDownload>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum MaybeFile {
+        File(GbModDownload),
+        Malformed(IgnoredAny),
+    }
+    let Some(files) = Option::<Vec<MaybeFile>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let files = files
+        .into_iter()
+        .filter_map(|f| match f {
+            MaybeFile::File(file) => Some(file),
+            MaybeFile::Malformed(_) => {
+                eprintln!("Skipping a malformed file entry");
+                None
+            }
+        })
+        .collect();
+    Ok(Some(files))
+}
+
+fn skip_malformed_file_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<GbModDownload>, D::Error> {
+    Ok(skip_malformed_files(deserializer)?.unwrap_or_default())
+}
+
+/// Turns GameBanana's scan results into a badge, a file is only clean when both virus scans passed
+/// and the analysis found nothing, results that aren't known yet show it as unverified
+pub fn scan_badge(file: &GbModDownload) -> ScanBadge {
+    let av = [&file.clam_av_result, &file.avast_av_result].map(|r| r.trim().to_lowercase());
+    let flagged = ["infected", "suspicious", "malware", "unsafe"];
+    if av.iter().any(|r| flagged.iter().any(|f| r.contains(f))) {
+        return ScanBadge::Flagged;
+    }
+    let analysed = matches!(file.analysis_state.to_lowercase().as_str(), "" | "done");
+    let passed = matches!(file.analysis_result_code.to_lowercase().as_str(), "" | "ok");
+    match av.iter().all(|r| r == "clean") && analysed && passed && !file.contains_exe {
+        true => ScanBadge::Clean,
+        false => ScanBadge::Pending,
+    }
+}
+
+fn scan_summary(file: &GbModDownload) -> String {
+    let result = |r: &String| match r.is_empty() {
+        true => "not scanned".to_owned(),
+        false => r.clone(),
+    };
+    let mut summary = format!(
+        "ClamAV: {}, Avast: {}",
+        result(&file.clam_av_result),
+        result(&file.avast_av_result)
+    );
+    if !file.analysis_result.is_empty() {
+        summary += &format!(", {}", file.analysis_result);
+    }
+    summary
+}
+
 impl From<GbModDownload> for Download {
     fn from(value: GbModDownload) -> Self {
+        let scan = scan_badge(&value);
+        let scan_summary = scan_summary(&value).into();
         Self {
             failed: false,
             id: value.id as i32,
@@ -71,6 +230,162 @@ impl From<GbModDownload> for Download {
             size: value.filesize as i32,
             url: value.download_url.into(),
             inprogress: false,
+            install: true,
+            priority: 0,
+            queued: false,
+            md5: value.md5_checksum.into(),
+            status: Default::default(),
+            contains_exe: value.contains_exe,
+            clam_av_result: value.clam_av_result.into(),
+            avast_av_result: value.avast_av_result.into(),
+            scan,
+            scan_summary,
+        }
+    }
+}
+
+/// Downloads waiting for one of the [`max_downloads`] slots, the highest priority one is started
+/// next and downloads with the same priority start in the order they were queued
+struct DownloadQueue {
+    running: usize,
+    next_seq: u64,
+    waiting: Vec<QueuedDownload>,
+}
+
+struct QueuedDownload {
+    id: i32,
+    priority: i32,
+    seq: u64,
+    start: oneshot::Sender<()>,
+}
+
+impl DownloadQueue {
+    fn start_next(&mut self) {
+        let max = max_downloads();
+        while self.running < max {
+            let Some(idx) = self
+                .waiting
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, d)| (d.priority, Reverse(d.seq)))
+                .map(|(i, _)| i)
+            else {
+                return;
+            };
+            let queued = self.waiting.remove(idx);
+            // the receiver is gone if the download was cancelled while it waited
+            if queued.start.send(()).is_ok() {
+                self.running += 1;
+            }
+        }
+    }
+}
+
+/// Holds a download slot until it is dropped, dropping it while it's still queued leaves the queue
+struct DownloadSlot {
+    seq: u64,
+}
+
+impl Drop for DownloadSlot {
+    fn drop(&mut self) {
+        let Ok(mut queue) = DOWNLOAD_QUEUE.lock() else {
+            return;
+        };
+        match queue.waiting.iter().position(|d| d.seq == self.seq) {
+            Some(idx) => {
+                queue.waiting.remove(idx);
+            }
+            None => queue.running = queue.running.saturating_sub(1),
+        }
+        queue.start_next();
+    }
+}
+
+async fn acquire_download_slot(id: i32, priority: i32) -> DownloadSlot {
+    let (tx, rx) = oneshot::channel();
+    let slot = match DOWNLOAD_QUEUE.lock() {
+        Ok(mut queue) => {
+            let seq = queue.next_seq;
+            queue.next_seq += 1;
+            queue.waiting.push(QueuedDownload {
+                id,
+                priority,
+                seq,
+                start: tx,
+            });
+            queue.start_next();
+            DownloadSlot { seq }
+        }
+        // a poisoned queue shouldn't stop downloads, they just aren't limited anymore
+        Err(_) => return DownloadSlot { seq: u64::MAX },
+    };
+    let _ = rx.await;
+    slot
+}
+
+/// How many mod files are downloaded at once, anything past this waits for a free slot
+pub fn max_downloads() -> usize {
+    R4D_CFG.try_lock().map_or(DEFAULT_MAX_DOWNLOADS, |cfg| {
+        cfg.max_concurrent_downloads.max(1) as usize
+    })
+}
+
+/// Starts queued downloads for any slots that are free, used after the limit was raised
+pub fn start_queued_downloads() {
+    if let Ok(mut queue) = DOWNLOAD_QUEUE.lock() {
+        queue.start_next();
+    }
+}
+
+/// Whether a download queued now would have to wait for a free slot
+pub fn download_queue_full() -> bool {
+    DOWNLOAD_QUEUE
+        .lock()
+        .is_ok_and(|queue| queue.running >= max_downloads())
+}
+
+/// Gives a queued download a higher priority than anything else waiting so it starts next,
+/// downloads that are already running are left alone
+pub fn move_download_to_front(id: i32) {
+    let Ok(mut queue) = DOWNLOAD_QUEUE.lock() else {
+        return;
+    };
+    let top = queue.waiting.iter().map(|d| d.priority).max().unwrap_or(0);
+    if let Some(download) = queue.waiting.iter_mut().find(|d| d.id == id) {
+        download.priority = top + 1;
+    }
+}
+
+/// What a running download reports back to the window it was started from
+pub enum DownloadProgress {
+    /// the download got a slot and left the queue
+    Started,
+    /// the size of a chunk that was written
+    Received(usize),
+    /// the download failed and is tried again after a short wait
+    Retrying { attempt: i32, retries: i32 },
+}
+
+/// A running download as reported by the status server
+#[derive(Serialize, Clone, Debug)]
+pub struct DownloadStatus {
+    pub id: i32,
+    pub name: String,
+    pub url: String,
+    /// size in bytes as reported by GameBanana
+    pub size: i32,
+    /// bytes downloaded so far
+    pub progress: i32,
+}
+
+impl From<Download> for DownloadStatus {
+    fn from(value: Download) -> Self {
+        Self {
+            id: value.id,
+            name: value.name.to_string(),
+            url: value.url.to_string(),
+            size: value.size,
+            progress: value.progress,
         }
     }
 }
@@ -85,7 +400,11 @@ impl PartialEq<i32> for Download {
 pub struct GbMod {
     #[serde(rename(deserialize = "_sName"))]
     pub name: String,
-    #[serde(rename(deserialize = "_aFiles"))]
+    #[serde(
+        rename(deserialize = "_aFiles"),
+        default,
+        deserialize_with = "skip_malformed_files"
+    )]
     pub files: Option<Vec<GbModDownload>>,
     #[serde(rename(deserialize = "_sText"))]
     pub text: Option<String>,
@@ -93,46 +412,100 @@ pub struct GbMod {
     pub submitter: Option<GbSubmitter>,
 }
 
+/// A file as returned by the legacy Core/Item/Data api
+#[derive(Deserialize, Clone, Debug)]
+pub struct GbLegacyFile {
+    #[serde(rename(deserialize = "_idRow"), default)]
+    pub id: i32,
+    #[serde(rename(deserialize = "_sFile"), default)]
+    pub file: String,
+    #[serde(rename(deserialize = "_nFilesize"), default)]
+    pub filesize: u32,
+    #[serde(rename(deserialize = "_sDescription"), default)]
+    pub description: String,
+    #[serde(rename(deserialize = "_tsDateAdded"), default)]
+    pub date_added: u32,
+    #[serde(rename(deserialize = "_nDownloadCount"), default)]
+    pub download_count: u32,
+    #[serde(rename(deserialize = "_sMd5Checksum"), default)]
+    pub md5_checksum: String,
+    #[serde(rename(deserialize = "_sDownloadUrl"), default)]
+    pub download_url: String,
+}
+
+/// The legacy api gives an empty array instead of an object when there are no files
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum GbLegacyFiles {
+    Files(HashMap<String, GbLegacyFile>),
+    Empty(Vec<GbLegacyFile>),
+}
+
+impl From<GbLegacyFile> for GbModDownload {
+    fn from(value: GbLegacyFile) -> Self {
+        Self {
+            id: value.id,
+            file: value.file,
+            filesize: value.filesize,
+            description: value.description,
+            date_added: value.date_added,
+            download_count: value.download_count,
+            md5_checksum: value.md5_checksum,
+            download_url: value.download_url,
+            clam_av_result: String::new(),
+            avast_av_result: String::new(),
+            analysis_state: String::new(),
+            analysis_result: String::new(),
+            analysis_result_code: String::new(),
+            contains_exe: false,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GBSearch {
     #[serde(rename(deserialize = "_idRow"))]
-    id: u64,
+    pub id: u64,
     #[serde(rename(deserialize = "_sModelName"), default)]
-    model_name: String,
+    pub model_name: String,
     #[serde(rename(deserialize = "_sSingularTitle"), default)]
-    title: String,
+    pub title: String,
     #[serde(rename(deserialize = "_sIconClasses"), default)]
-    icon_classes: String,
+    pub icon_classes: String,
     #[serde(rename(deserialize = "_sName"), default)]
-    name: String,
+    pub name: String,
     #[serde(rename(deserialize = "_sProfileUrl"), default)]
-    profile_url: String,
+    pub profile_url: String,
     #[serde(rename(deserialize = "_tsDateAdded"))]
-    date_added: i64,
+    pub date_added: i64,
     #[serde(rename(deserialize = "_bHasFiles"), default)]
-    has_files: bool,
+    pub has_files: bool,
     #[serde(rename(deserialize = "_aSubmitter"))]
-    submitter: GbSubmitter,
+    pub submitter: GbSubmitter,
     #[serde(rename(deserialize = "_tsDateUpdated"), default)]
-    date_updated: i64,
+    pub date_updated: i64,
     #[serde(rename(deserialize = "_bIsNsfw"), default)]
-    is_nsfw: bool,
+    pub is_nsfw: bool,
     #[serde(rename(deserialize = "_sInitialVisibility"), default)]
-    initial_visibility: String,
+    pub initial_visibility: String,
     #[serde(rename(deserialize = "_nLikeCount"), default)]
-    like_count: i32,
+    pub like_count: i32,
     #[serde(rename(deserialize = "_nPostCount"), default)]
-    post_count: i32,
+    pub post_count: i32,
     #[serde(rename(deserialize = "_bWasFeatured"), default)]
-    was_featured: bool,
+    pub was_featured: bool,
     #[serde(rename(deserialize = "_nViewCount"), default)]
-    view_count: i32,
+    pub view_count: i32,
     #[serde(rename(deserialize = "_bIsOwnedByAccessor"), default)]
-    is_owned_by_accessor: bool,
+    pub is_owned_by_accessor: bool,
     #[serde(rename(deserialize = "_aPreviewMedia"))]
-    preview_media: GbPreview,
-    #[serde(rename(deserialize = "_aFiles"), default)]
-    files: Vec<GbModDownload>,
+    pub preview_media: GbPreview,
+    #[serde(
+        rename(deserialize = "_aFiles"),
+        default,
+        deserialize_with = "skip_malformed_file_list"
+    )]
+    pub files: Vec<GbModDownload>,
 }
 
 impl From<GBSearch> for GbPreviewData {
@@ -161,7 +534,44 @@ impl From<GBSearch> for GbPreviewData {
             updated: updated.into(),
             image_url: imgurl.into(),
             image_loaded: false,
+            image_failed: false,
             submitted: added.into(),
+            has_files: value.has_files,
+            file_count: value.files.len() as i32,
+            files_size: total_file_size(&value.files),
+            profile_url: value.profile_url.into(),
+        }
+    }
+}
+
+/// Summary of a search result that gets copied to the clipboard for troubleshooting
+#[derive(Serialize)]
+pub struct GbDebugInfo {
+    pub id: i32,
+    pub name: String,
+    pub author: String,
+    pub item_type: String,
+    pub submitted: String,
+    pub updated: String,
+    pub url: String,
+    pub image_url: String,
+}
+
+impl From<GbPreviewData> for GbDebugInfo {
+    fn from(value: GbPreviewData) -> Self {
+        Self {
+            id: value.id,
+            name: value.name.to_string(),
+            author: value.author.name.to_string(),
+            item_type: value.item_type.to_string(),
+            submitted: value.submitted.to_string(),
+            updated: value.updated.to_string(),
+            url: if value.profile_url.is_empty() {
+                format!("{GB_DOMAIN}/mods/{}", value.id)
+            } else {
+                value.profile_url.to_string()
+            },
+            image_url: value.image_url.to_string(),
         }
     }
 }
@@ -211,28 +621,45 @@ pub struct GbPreviewImage {
 
 #[derive(Clone, Debug)]
 pub struct GbDmmItem {
+    /// the mod's id, this is the last number in the url
     pub item_id: i32,
     #[allow(dead_code)]
     pub itemtype: String,
+    /// the id of the file to download, this is the first number in the url
     pub file_id: i32,
 }
 
+/// A modpack shared as a one-click link, see [`create_loadout_url`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SharedLoadout {
+    pub name: String,
+    pub mods: Vec<SharedLoadoutMod>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SharedLoadoutMod {
+    /// the GameBanana mod id
+    pub id: i32,
+    /// the id of the file the mod was installed from
+    pub file: i32,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GbSearchResults {
     #[serde(rename(serialize = "_aMetadata", deserialize = "_aMetadata"))]
-    metadata: GbMetadata,
+    pub metadata: GbMetadata,
     #[serde(rename(serialize = "_aRecords", deserialize = "_aRecords"))]
-    records: Vec<GBSearch>,
+    pub records: Vec<GBSearch>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GbMetadata {
     #[serde(rename(serialize = "_nRecordCount", deserialize = "_nRecordCount"))]
-    record_count: i32,
+    pub record_count: i32,
     #[serde(rename(serialize = "_nPerpage", deserialize = "_nPerpage"))]
-    perpage: i32,
+    pub perpage: i32,
     #[serde(rename(serialize = "_bIsComplete", deserialize = "_bIsComplete"))]
-    is_complete: bool,
+    pub is_complete: bool,
 }
 
 pub enum GbSearchSort {
@@ -270,22 +697,63 @@ impl From<i32> for GbSearchSort {
     }
 }
 
+/// Parses a one-click url, these are formatted as
+/// `divamodmanager:https://gamebanana.com/mmdl/<file id>,<item type>,<item id>`
 pub fn parse_dmm_url(dmm_url: String) -> Option<GbDmmItem> {
     // check if this is a proper dmm 1 click url
-    if !dmm_url.starts_with("divamodmanager:https://gamebanana.com/mmdl/") {
-        return None;
-    }
+    let item = dmm_url.strip_prefix("divamodmanager:https://gamebanana.com/mmdl/")?;
+    // some browsers hand the url over with the commas percent encoded
+    let item = item.replace("%2C", ",").replace("%2c", ",");
 
-    let mod_regex = Regex::new(r"([0-9]+),(.+),([0-9]+)").unwrap();
-    let Some(m_info) = mod_regex.captures(dmm_url.as_str()) else {
+    // anchored so digits in the item type or any trailing segments can't be picked up as an id
+    let mod_regex = Regex::new(r"^([0-9]+),([A-Za-z]+),([0-9]+)/?$").unwrap();
+    let Some(m_info) = mod_regex.captures(&item) else {
         println!("Sorry, no fucks in here");
         return None;
     };
-    return Some(GbDmmItem {
-        file_id: m_info.get(1).unwrap().as_str().parse().unwrap(),
-        itemtype: m_info.get(2).unwrap().as_str().to_string(),
-        item_id: m_info.get(3).unwrap().as_str().parse().unwrap(),
-    });
+    Some(GbDmmItem {
+        file_id: m_info.get(1)?.as_str().parse().ok()?,
+        itemtype: m_info.get(2)?.as_str().to_string(),
+        item_id: m_info.get(3)?.as_str().parse().ok()?,
+    })
+}
+
+/// Encodes a loadout as `divamodmanager:loadout:<base64 json>` so it goes through the same url
+/// handler as GameBanana's one-click links
+pub fn create_loadout_url(loadout: &SharedLoadout) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let json = sonic_rs::to_string(loadout)?;
+    Ok(format!(
+        "{LOADOUT_URL_PREFIX}{}",
+        Base64UrlUnpadded::encode_string(json.as_bytes())
+    ))
+}
+
+pub fn parse_loadout_url(url: &str) -> Option<SharedLoadout> {
+    let encoded = url.trim().strip_prefix(LOADOUT_URL_PREFIX)?;
+    // browsers sometimes add a trailing slash when handing the url over
+    let json = Base64UrlUnpadded::decode_vec(encoded.trim_end_matches('/')).ok()?;
+    match sonic_rs::from_slice::<SharedLoadout>(&json) {
+        Ok(loadout) => Some(loadout),
+        Err(e) => {
+            eprintln!("Invalid shared loadout: {e}");
+            None
+        }
+    }
+}
+
+/// Removes search results that are already shown or appear more than once, keeping the first
+fn dedupe_results(items: &mut Vec<GbPreviewData>, shown: &HashSet<i32>) {
+    let mut seen = shown.clone();
+    items.retain(|item| seen.insert(item.id));
+}
+
+/// Gets the mod id out of a plain GameBanana mod url, e.g. `https://gamebanana.com/mods/12345`
+pub fn parse_gb_url(url: &str) -> Option<i32> {
+    let url_regex =
+        Regex::new(r"^(?:https?://)?(?:www\.)?gamebanana\.com/mods/([0-9]+)/?(?:[?#].*)?$")
+            .unwrap();
+    let m_info = url_regex.captures(url.trim())?;
+    m_info.get(1)?.as_str().parse().ok()
 }
 
 pub async fn init(ui: &App, url_rx: Receiver<String>, dark_rx: broadcast::Receiver<ColorScheme>) {
@@ -293,18 +761,53 @@ pub async fn init(ui: &App, url_rx: Receiver<String>, dark_rx: broadcast::Receiv
 
     ui.global::<GameBananaLogic>()
         .on_search(move |search, page, sort| {
-            let ui_search_handle = ui_search_handle.clone();
             let ui_result_handle = ui_search_handle.clone();
-            ui_search_handle.unwrap().set_s_prog_vis(true);
-            tokio::spawn(async move {
-                match search_gb(search.to_string(), page.clone(), sort.clone()).await {
+            let task = tokio::spawn(async move {
+                // loading more pages is a click rather than typing, so it's sent right away
+                if page == 1 {
+                    sleep(SEARCH_DEBOUNCE).await;
+                }
+                let _ = ui_result_handle.upgrade_in_event_loop(|ui| ui.set_s_prog_vis(true));
+                let phase_handle = ui_result_handle.clone();
+                let phase = move |phase: &str| {
+                    let phase = SharedString::from(phase);
+                    let _ = phase_handle.upgrade_in_event_loop(move |ui| ui.set_s_status(phase));
+                };
+                let per_page = R4D_CFG
+                    .try_lock()
+                    .map_or(GB_DEFAULT_PER_PAGE, |cfg| cfg.search_per_page);
+                let model = TYPE_FILTERS
+                    .get(TYPE_FILTER.load(Ordering::Relaxed))
+                    .unwrap_or(&"Mod");
+                match search_gb(search.to_string(), page, sort, model, per_page, phase).await {
                     Ok(res) => {
+                        if page == 1 {
+                            prefetch_mod_info(
+                                res.records
+                                    .iter()
+                                    .take(PREFETCH_RESULTS)
+                                    .map(|r| r.id as i32)
+                                    .collect(),
+                            );
+                        }
+                        let show_nsfw = R4D_CFG.try_lock().is_ok_and(|cfg| cfg.show_nsfw);
+                        // filtered and duplicate results make the row count unreliable, so the
+                        // pages are compared to the record count instead
+                        let complete = res.metadata.is_complete
+                            || res.records.is_empty()
+                            || page * per_page.clamp(1, GB_MAX_PER_PAGE)
+                                >= res.metadata.record_count;
                         let _ = ui_result_handle.upgrade_in_event_loop(move |ui| {
-                            let mut items = vec![];
+                            ui.set_s_status("".into());
+                            let mut items: Vec<GbPreviewData> = vec![];
                             for i in res.records.clone() {
+                                if i.is_nsfw && !show_nsfw {
+                                    continue;
+                                }
                                 items.push(i.into());
                             }
                             if page == 1 {
+                                dedupe_results(&mut items, &HashSet::new());
                                 ui.set_s_results(ModelRc::new(VecModel::from(items.clone())));
                                 ui.set_n_results(res.metadata.record_count);
                             } else {
@@ -319,31 +822,103 @@ pub async fn init(ui: &App, url_rx: Receiver<String>, dark_rx: broadcast::Receiv
                                         return;
                                     }
                                 };
+                                // mods added while paging shift the pages, so a mod from the
+                                // previous page can show up again, the one already shown is kept
+                                let shown: HashSet<i32> = results.iter().map(|r| r.id).collect();
+                                dedupe_results(&mut items, &shown);
                                 for i in items {
                                     results.push(i);
                                 }
                             }
+                            ui.set_s_complete(complete);
                             ui.set_s_prog_vis(false);
-                            for i in res.records.clone() {
-                                let weak = ui.as_weak();
-                                tokio::spawn(async move {
-                                    get_and_set_preview_image(weak.clone(), i.clone()).await;
-                                });
+                            // the visible range may not change when the results are replaced
+                            if let Ok((first, last)) = VISIBLE_RANGE.lock().map(|r| *r) {
+                                load_visible_images(&ui, first, last);
                             }
                         });
                     }
-                    Err(e) => open_error_window(e.to_string()),
+                    Err(e) => {
+                        let _ = ui_result_handle.upgrade_in_event_loop(|ui| {
+                            ui.set_s_status("".into());
+                            ui.set_s_prog_vis(false);
+                        });
+                        open_error_window(e.to_string());
+                    }
+                }
+            });
+            if let Ok(mut pending) = PENDING_SEARCH.lock() {
+                if let Some(previous) = pending.replace(task.abort_handle()) {
+                    previous.abort();
                 }
+            }
+        });
+
+    let weak = ui.as_weak();
+    ui.global::<GameBananaLogic>()
+        .on_load_images(move |first, last| {
+            let ui = weak.unwrap();
+            load_visible_images(&ui, first, last);
+        });
+
+    let weak = ui.as_weak();
+    ui.global::<GameBananaLogic>().on_retry_image(move |item| {
+        let queued = match LOADING_IMAGES.lock() {
+            Ok(mut loading) => loading.insert(item.id),
+            Err(_) => false,
+        };
+        if queued {
+            let weak = weak.clone();
+            tokio::spawn(async move {
+                get_and_set_preview_image(weak, item.id, item.image_url.to_string()).await;
             });
+        }
+    });
+
+    ui.global::<GameBananaLogic>()
+        .on_set_type_filter(move |idx| {
+            TYPE_FILTER.store(idx.max(0) as usize, Ordering::Relaxed);
         });
 
+    let weak = ui.as_weak();
+    ui.global::<GameBananaLogic>().on_redownload_all(move || {
+        tokio::spawn(redownload_all(weak.clone()));
+    });
+
+    let weak = ui.as_weak();
+    ui.global::<GameBananaLogic>().on_copy_info(move |item| {
+        match sonic_rs::to_string_pretty(&GbDebugInfo::from(item)) {
+            Ok(info) => weak.unwrap().invoke_copy_to_clipboard(info.into()),
+            Err(e) => open_error_window(e.to_string()),
+        }
+    });
+
     let weak = ui.as_weak();
     let darkrrx = dark_rx.resubscribe();
-    ui.global::<GameBananaLogic>().on_list_files(move |item| {
+    ui.global::<GameBananaLogic>().on_open_url(move |url| {
+        let Some(id) = parse_gb_url(url.as_str()) else {
+            open_error_window(format!("{url} is not a GameBanana mod url"));
+            return;
+        };
         let weak = weak.clone();
         let dark_rx = darkrrx.resubscribe();
-        let deets = create_deets_window(item, weak, dark_rx);
-        deets.show().unwrap();
+        tokio::spawn(async move {
+            match fetch_mod(id).await {
+                Ok(m) => {
+                    let _ = slint::invoke_from_event_loop(move || {
+                        let deets = create_deets_window(m.into(), weak, dark_rx);
+                        deets.show().unwrap();
+                    });
+                }
+                Err(e) => open_error_window(e.to_string()),
+            }
+        });
+    });
+
+    let weak = ui.as_weak();
+    let darkrrx = dark_rx.resubscribe();
+    ui.global::<GameBananaLogic>().on_list_files(move |item| {
+        open_details(item, weak.clone(), darkrrx.resubscribe(), None);
     });
     let ui_oneclick_handle = ui.as_weak();
     let _ = handle_dmm_oneclick(url_rx, ui_oneclick_handle, dark_rx.resubscribe());
@@ -357,6 +932,14 @@ pub fn handle_dmm_oneclick(
     return tokio::spawn(async move {
         while !url_rx.is_closed() {
             if let Some(url) = url_rx.recv().await {
+                if let Some(loadout) = parse_loadout_url(&url) {
+                    tokio::spawn(install_loadout(loadout, ui_handle.clone()));
+                    continue;
+                }
+                if let Some(url) = url.strip_prefix(ONECLICK_TEST_PREFIX) {
+                    finish_oneclick_test(check_oneclick_url(url.to_owned()).await);
+                    continue;
+                }
                 let item = match parse_dmm_url(url) {
                     Some(item) => item,
                     None => continue,
@@ -371,22 +954,8 @@ pub fn handle_dmm_oneclick(
                 let weak = ui_handle.clone();
                 let rx = dark_rx.resubscribe();
                 let _ = slint::invoke_from_event_loop(move || {
-                    let deets = create_deets_window(m.clone().into(), weak, rx);
-                    let files: VecModel<Download> = VecModel::default();
-                    for file in m.files.clone() {
-                        let mut f: Download = file.clone().into();
-                        if f.id == item.file_id {
-                            f.inprogress = true;
-                        }
-                        files.push(f);
-                    }
-                    deets.set_files(ModelRc::new(files));
-                    if let Some(file) = m.files.iter().find(|f| f.id == item.file_id) {
-                        deets
-                            .global::<GameBananaLogic>()
-                            .invoke_download(file.clone().into());
-                    }
-                    deets.show().unwrap();
+                    let files = m.files.clone();
+                    open_details(m.into(), weak, rx, Some((files, item.file_id)));
                 });
                 // cre
             }
@@ -395,13 +964,87 @@ pub fn handle_dmm_oneclick(
     });
 }
 
-pub async fn get_and_set_preview_image(weak: Weak<App>, item: GBSearch) {
+/// Goes through the same steps as a one-click url without opening the details, the error names the
+/// step that failed
+async fn check_oneclick_url(url: String) -> Result<String, String> {
+    let item = parse_dmm_url(url.clone())
+        .ok_or(format!("at parsing, {url} isn't a valid one-click url"))?;
+    let m = fetch_mod(item.item_id)
+        .await
+        .map_err(|e| format!("at fetching mod {}:\n{e}", item.item_id))?;
+    let file = oneclick_file(&m.files, &item).ok_or(format!(
+        "at queueing, {} has no file {}",
+        m.name, item.file_id
+    ))?;
+    Ok(format!("{} - {}", m.name, file.file))
+}
+
+/// The file a one-click url asks for out of the mod's files, the mod itself is fetched by `item_id`
+fn oneclick_file<'a>(files: &'a [GbModDownload], item: &GbDmmItem) -> Option<&'a GbModDownload> {
+    files.iter().find(|f| f.id == item.file_id)
+}
+
+/// Queues the preview images for the results in `first..last`, results outside of that range are no longer wanted
+pub fn load_visible_images(ui: &App, first: i32, last: i32) {
+    if let Ok(mut range) = VISIBLE_RANGE.lock() {
+        *range = (first, last);
+    }
+    let model = ui.get_s_results();
+    let first = max(first, 0) as usize;
+    let last = min(max(last, 0) as usize, model.row_count());
+    let visible: Vec<GbPreviewData> = (first..last).filter_map(|i| model.row_data(i)).collect();
+    if let Ok(mut ids) = VISIBLE_IMAGES.lock() {
+        *ids = visible.iter().map(|r| r.id).collect();
+    }
+    for item in visible {
+        if item.image_loaded {
+            continue;
+        }
+        let queued = match LOADING_IMAGES.lock() {
+            Ok(mut loading) => loading.insert(item.id),
+            Err(_) => false,
+        };
+        if queued {
+            let weak = ui.as_weak();
+            tokio::spawn(async move {
+                get_and_set_preview_image(weak, item.id, item.image_url.to_string()).await;
+            });
+        }
+    }
+}
+
+pub async fn get_and_set_preview_image(weak: Weak<App>, id: i32, url: String) {
+    let _permit = IMAGE_LOADS.acquire().await;
+    if !VISIBLE_IMAGES.lock().is_ok_and(|ids| ids.contains(&id)) {
+        // scrolled out of view before the download started, it will be queued again when visible
+        if let Ok(mut loading) = LOADING_IMAGES.lock() {
+            loading.remove(&id);
+        }
+        return;
+    }
     let mut buffer = missing_image_buf();
-    if let Some(preview) = item.preview_media.images.first() {
-        if let Ok(buf) = get_image(format!("{}/{}", preview.base_url, preview.file)).await {
-            buffer = buf;
+    let mut failed = false;
+    if !url.is_empty() {
+        let mut attempt = 0;
+        loop {
+            match get_image(url.clone(), current_thumb_dimensions()).await {
+                Ok(buf) => buffer = buf,
+                Err(e) if attempt < IMAGE_RETRIES && is_transient(e.as_ref()) => {
+                    attempt += 1;
+                    sleep(Duration::from_secs(attempt)).await;
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("Unable to load the preview of {id}: {e}");
+                    failed = true;
+                }
+            }
+            break;
         }
     }
+    if let Ok(mut loading) = LOADING_IMAGES.lock() {
+        loading.remove(&id);
+    }
     let _ = weak.upgrade_in_event_loop(move |ui| {
         let image = slint::Image::from_rgba8(buffer);
         let model = ui.get_s_results();
@@ -413,9 +1056,10 @@ pub async fn get_and_set_preview_image(weak: Weak<App>, item: GBSearch) {
         };
         for i in 0..results.row_count() {
             let mut row = results.row_data(i).unwrap();
-            if row.id == item.id as i32 {
+            if row.id == id {
                 row.image = image;
                 row.image_loaded = true;
+                row.image_failed = failed;
                 results.set_row_data(i, row);
                 return;
             }
@@ -423,110 +1067,743 @@ pub async fn get_and_set_preview_image(weak: Weak<App>, item: GBSearch) {
     });
 }
 
-pub async fn get_image(
-    url: String,
-) -> Result<SharedPixelBuffer<Rgba8Pixel>, Box<dyn Error + Sync + Send>> {
-    let client = reqwest::Client::new();
-    let req = client.get(url);
-    let res = req.send().await?;
-    let bytes = res.bytes().await?;
-    let image = image::load_from_memory(&bytes)?;
-    let image = image
-        .resize(880 as u32, 496 as u32, image::imageops::FilterType::Nearest)
-        .into_rgba8();
-    let buffer = SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
-        image.as_raw(),
-        image.width(),
-        image.height(),
-    );
-    Ok(buffer)
+/// Returns the size that search result thumbnails are resized to for the given setting
+pub fn thumb_dimensions(size: i32) -> (u32, u32) {
+    match size {
+        0 => (440, 248),
+        2 => (880, 496),
+        _ => (660, 372),
+    }
 }
 
-pub async fn search_gb(
-    search: String,
-    page: i32,
-    sort: i32,
-) -> Result<GbSearchResults, Box<dyn Error + Send + Sync>> {
-    let client = reqwest::Client::new();
-    let req = client.get(format!("{GB_DOMAIN}/{GB_MOD_SEARCH}")).query(&[
-        ("_sSearchString", search),
-        ("_nPage", page.to_string()),
-        ("_nPerpage", "30".to_owned()),
-        ("_sOrder", GbSearchSort::from(sort).into()),
-        ("_idGameRow", GB_DIVA_ID.to_string()),
-        ("_sModelName", "Mod".to_owned()),
-    ]);
-    // req.
-    let res = req.send().await?.text().await?;
-    match sonic_rs::from_str::<GbSearchResults>(&res) {
-        Ok(results) => Ok(results),
-        Err(e) => {
-            eprintln!("{}", res); // log the res that failed to parse
-            Err(e.into())
-        }
+fn current_thumb_dimensions() -> (u32, u32) {
+    match R4D_CFG.try_lock() {
+        Ok(cfg) => thumb_dimensions(cfg.thumb_size),
+        Err(_) => thumb_dimensions(1),
     }
-    // Ok(sonic_rs::from_str::<GbSearchResults>(&res)?)
 }
 
-pub async fn fetch_mod_info(mod_id: i32) -> Result<GbMod, Box<dyn Error + Send + Sync>> {
-    let client = reqwest::Client::new();
-    let req = client.get(format!(
-        "{}/{}/{}?_csvProperties=_aFiles,_sText,_idRow,_sName,_aSubmitter",
-        GB_DOMAIN,
-        GB_MOD_DATA,
-        mod_id.clone()
-    ));
-    let text = req.send().await?.text().await?;
-    match sonic_rs::from_str::<GbMod>(&text) {
-        Ok(module) => Ok(module),
-        Err(e) => {
-            eprintln!("{}", text); // log the res that failed to parse
-            Err(e.into())
-        }
+/// Folder preview images are kept in between runs, each image is stored as `<hash>.img` with its
+/// ETag and Last-Modified headers in `<hash>.etag` so it can be revalidated instead of downloaded
+fn image_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = get_portable_dir() {
+        return Some(dir.join("cache").join("images"));
     }
+    dirs::cache_dir().map(|dir| dir.join("rust4diva").join("images"))
 }
 
-pub fn missing_image_buf() -> SharedPixelBuffer<Rgba8Pixel> {
-    let bytes = include_bytes!("../ui/assets/missing-image.png");
-    let image = image::load_from_memory(bytes).unwrap();
-    let image = image
-        .resize(440 as u32, 248 as u32, image::imageops::FilterType::Nearest)
-        .into_rgba8();
-    SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(image.as_raw(), image.width(), image.height())
+/// Reads an image stored by a previous run along with the ETag and Last-Modified it was served with
+async fn read_cached_image(path: &PathBuf) -> Option<(Vec<u8>, String, String)> {
+    let bytes = tokio::fs::read(path.with_extension("img")).await.ok()?;
+    // the modified time is used as the last use when the cache is pruned
+    if let Ok(file) = File::options().write(true).open(path.with_extension("img")) {
+        let _ = file.set_modified(std::time::SystemTime::now());
+    }
+    let validators = tokio::fs::read_to_string(path.with_extension("etag"))
+        .await
+        .unwrap_or_default();
+    let mut lines = validators.lines();
+    let etag = lines.next().unwrap_or_default().to_owned();
+    let modified = lines.next().unwrap_or_default().to_owned();
+    Some((bytes, etag, modified))
 }
 
-pub fn _missing_image() -> slint::Image {
-    slint::Image::from_rgba8(missing_image_buf())
+async fn write_cached_image(
+    path: &PathBuf,
+    bytes: &[u8],
+    etag: &str,
+    modified: &str,
+) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path.with_extension("img"), bytes).await?;
+    tokio::fs::write(path.with_extension("etag"), format!("{etag}\n{modified}")).await?;
+    if IMAGE_CACHE_WRITES.fetch_add(1, Ordering::Relaxed) % IMAGE_CACHE_PRUNE_EVERY == 0 {
+        tokio::task::spawn_blocking(prune_image_cache);
+    }
+    Ok(())
 }
 
-pub fn create_deets_window(
-    item: GbPreviewData,
-    weak: Weak<App>,
+/// Removes the least recently used images from the disk cache until it fits in
+/// [`MAX_IMAGE_CACHE_SIZE`]
+fn prune_image_cache() {
+    let Some(dir) = image_cache_dir() else {
+        return;
+    };
+    let mut images: Vec<(std::time::SystemTime, PathBuf, u64)> = walk_files(&dir)
+        .files
+        .into_iter()
+        .filter(|f| f.path.extension().is_some_and(|e| e == "img"))
+        .filter_map(|f| {
+            let used = std::fs::metadata(&f.path).and_then(|m| m.modified()).ok()?;
+            Some((used, f.path, f.size))
+        })
+        .collect();
+    let mut total: u64 = images.iter().map(|(_, _, size)| size).sum();
+    if total <= MAX_IMAGE_CACHE_SIZE {
+        return;
+    }
+    images.sort_by_key(|(used, _, _)| *used);
+    for (_, path, size) in images {
+        if total <= MAX_IMAGE_CACHE_SIZE {
+            break;
+        }
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("etag"));
+        total = total.saturating_sub(size);
+    }
+}
+
+/// Deletes every preview image cached on disk and in memory
+pub fn clear_image_cache() -> std::io::Result<()> {
+    if let Ok(mut cache) = IMAGE_CACHE.lock() {
+        cache.clear();
+    }
+    match image_cache_dir() {
+        Some(dir) if dir.exists() => std::fs::remove_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+async fn get_image_bytes(url: &String) -> Result<Vec<u8>, Box<dyn Error + Sync + Send>> {
+    if let Ok(cache) = IMAGE_CACHE.lock() {
+        if let Some(bytes) = cache.get(url) {
+            return Ok(bytes.clone());
+        }
+    }
+    let cache_path =
+        image_cache_dir().map(|dir| dir.join(format!("{:x}", Sha256::digest(url.as_bytes()))));
+    let stored = match &cache_path {
+        Some(path) => read_cached_image(path).await,
+        None => None,
+    };
+    let client = reqwest::Client::new();
+    let mut req = client.get(url);
+    if let Some((_, etag, modified)) = &stored {
+        if !etag.is_empty() {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if !modified.is_empty() {
+            req = req.header(IF_MODIFIED_SINCE, modified);
+        }
+    }
+    let res = match req.send().await {
+        Ok(res) => res,
+        Err(e) => {
+            // offline or GameBanana is unreachable, an outdated thumbnail beats none
+            let Some((bytes, _, _)) = stored else {
+                return Err(e.into());
+            };
+            eprintln!("Couldn't refresh {url}, using the cached copy: {e}");
+            remember_image(url, &bytes);
+            return Ok(bytes);
+        }
+    };
+    let bytes = match stored {
+        Some((bytes, _, _)) if res.status() == StatusCode::NOT_MODIFIED => bytes,
+        _ => {
+            if is_html_page(&res) {
+                let page = res.text().await?;
+                if is_nsfw_gate_page(&page) {
+                    return Err(
+                        format!("{url} is an NSFW preview that GameBanana won't serve").into(),
+                    );
+                }
+                return Err(format!(
+                    "{url} answered with a web page instead of an image ({})",
+                    page_title(&page)
+                )
+                .into());
+            }
+            let res = res.error_for_status()?;
+            let header = |name: reqwest::header::HeaderName| {
+                res.headers()
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_owned()
+            };
+            let etag = header(ETAG);
+            let modified = header(LAST_MODIFIED);
+            let bytes = res.bytes().await?.to_vec();
+            if let Some(path) = &cache_path {
+                if let Err(e) = write_cached_image(path, &bytes, &etag, &modified).await {
+                    eprintln!("Couldn't cache {url}: {e}");
+                }
+            }
+            bytes
+        }
+    };
+    remember_image(url, &bytes);
+    Ok(bytes)
+}
+
+/// Keeps an image's bytes in memory so it isn't read or requested again this run
+fn remember_image(url: &str, bytes: &[u8]) {
+    if let Ok(mut cache) = IMAGE_CACHE.lock() {
+        if cache.len() >= MAX_CACHED_IMAGES {
+            cache.clear();
+        }
+        cache.insert(url.to_owned(), bytes.to_vec());
+    }
+}
+
+fn resize_image(
+    bytes: &[u8],
+    (width, height): (u32, u32),
+) -> Result<SharedPixelBuffer<Rgba8Pixel>, Box<dyn Error + Sync + Send>> {
+    let image = image::load_from_memory(bytes)?;
+    let image = image
+        .resize(width, height, image::imageops::FilterType::Triangle)
+        .into_rgba8();
+    let buffer = SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+    );
+    Ok(buffer)
+}
+
+pub async fn get_image(
+    url: String,
+    size: (u32, u32),
+) -> Result<SharedPixelBuffer<Rgba8Pixel>, Box<dyn Error + Sync + Send>> {
+    let bytes = get_image_bytes(&url).await?;
+    resize_image(&bytes, size)
+}
+
+/// Resizes the loaded search result images to the current thumbnail size using the cached originals
+pub fn rescale_search_images(ui: &App) {
+    let urls: Vec<(i32, String)> = ui
+        .get_s_results()
+        .iter()
+        .filter(|r| r.image_loaded && !r.image_url.is_empty())
+        .map(|r| (r.id, r.image_url.to_string()))
+        .collect();
+    let weak = ui.as_weak();
+    tokio::spawn(async move {
+        let size = current_thumb_dimensions();
+        for (id, url) in urls {
+            let buffer = match get_image(url, size).await {
+                Ok(buffer) => buffer,
+                Err(e) => {
+                    eprintln!("{e}");
+                    continue;
+                }
+            };
+            let _ = weak.upgrade_in_event_loop(move |ui| {
+                let model = ui.get_s_results();
+                for i in 0..model.row_count() {
+                    let mut row = model.row_data(i).unwrap();
+                    if row.id == id {
+                        row.image = slint::Image::from_rgba8(buffer);
+                        model.set_row_data(i, row);
+                        return;
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Searches GameBanana, `phase` is called with the lang key of the step the search is currently on
+pub async fn search_gb(
+    search: String,
+    page: i32,
+    sort: i32,
+    model: &str,
+    per_page: i32,
+    phase: impl Fn(&str),
+) -> Result<GbSearchResults, Box<dyn Error + Send + Sync>> {
+    // pages are requested by number, so a smaller page size from the api only means more pages
+    let per_page = per_page.clamp(1, GB_MAX_PER_PAGE);
+    let client = reqwest::Client::new();
+    let mut query = vec![
+        ("_sSearchString", search),
+        ("_nPage", page.to_string()),
+        ("_nPerpage", per_page.to_string()),
+        ("_sOrder", GbSearchSort::from(sort).into()),
+        ("_idGameRow", GB_DIVA_ID.to_string()),
+    ];
+    // without a model name every type of submission for the game is searched
+    if !model.is_empty() {
+        query.push(("_sModelName", model.to_owned()));
+    }
+    let req = client
+        .get(format!("{GB_DOMAIN}/{GB_MOD_SEARCH}"))
+        .query(&query);
+    // req.
+    phase("label:search-fetching");
+    let res = req.send().await?;
+    phase("label:search-downloading");
+    let res = res.text().await?;
+    phase("label:search-parsing");
+    match sonic_rs::from_str::<GbSearchResults>(&res) {
+        Ok(results) => Ok(results),
+        Err(e) => {
+            eprintln!("{}", res); // log the res that failed to parse
+            Err(e.into())
+        }
+    }
+    // Ok(sonic_rs::from_str::<GbSearchResults>(&res)?)
+}
+
+/// Fetches a mod's files and description, falling back to the legacy api if the apiv11 response
+/// can't be parsed
+/// Same as [`fetch_mod_info`] but reuses a response from the last few minutes, used by the
+/// details view so going back to a mod that was just viewed doesn't wait on GameBanana again
+pub async fn cached_mod_info(mod_id: i32) -> Result<GbMod, Box<dyn Error + Send + Sync>> {
+    if let Ok(cache) = MOD_INFO_CACHE.lock() {
+        if let Some((fetched, module)) = cache.get(&mod_id) {
+            if fetched.elapsed() < MOD_INFO_TTL {
+                return Ok(module.clone());
+            }
+        }
+    }
+    let module = fetch_mod_info(mod_id).await?;
+    if let Ok(mut cache) = MOD_INFO_CACHE.lock() {
+        cache.retain(|_, (fetched, _)| fetched.elapsed() < MOD_INFO_TTL);
+        cache.insert(mod_id, (Instant::now(), module.clone()));
+    }
+    Ok(module)
+}
+
+pub fn invalidate_mod_info(mod_id: i32) {
+    if let Ok(mut cache) = MOD_INFO_CACHE.lock() {
+        cache.remove(&mod_id);
+    }
+}
+
+/// Loads the info of the first few results of a search in the background so opening them is instant
+fn prefetch_mod_info(ids: Vec<i32>) {
+    tokio::spawn(async move {
+        futures_util::stream::iter(ids.into_iter().map(|id| async move {
+            if let Err(e) = cached_mod_info(id).await {
+                eprintln!("Unable to prefetch mod {id}: {e}");
+            }
+        }))
+        .buffer_unordered(2)
+        .collect::<Vec<_>>()
+        .await;
+    });
+}
+
+pub async fn fetch_mod_info(mod_id: i32) -> Result<GbMod, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+    let req = client.get(format!(
+        "{}/{}/{}?_csvProperties=_aFiles,_sText,_idRow,_sName,_aSubmitter",
+        GB_DOMAIN,
+        GB_MOD_DATA,
+        mod_id.clone()
+    ));
+    let text = req.send().await?.text().await?;
+    match sonic_rs::from_str::<GbMod>(&text) {
+        Ok(module) => {
+            println!("Mod info for {mod_id} served by {GB_MOD_DATA}");
+            Ok(module)
+        }
+        Err(e) => {
+            eprintln!("{}", text); // log the res that failed to parse
+            eprintln!("Unable to parse {GB_MOD_DATA} response for {mod_id}: {e}");
+            match fetch_mod_info_legacy(mod_id).await {
+                Ok(module) => {
+                    println!("Mod info for {mod_id} served by {GB_LEGACY_DATA}");
+                    Ok(module)
+                }
+                // the original error is more useful, the legacy api is only a fallback
+                Err(legacy) => {
+                    eprintln!("{legacy}");
+                    Err(e.into())
+                }
+            }
+        }
+    }
+}
+
+async fn fetch_mod_info_legacy(mod_id: i32) -> Result<GbMod, Box<dyn Error + Send + Sync>> {
+    let req = reqwest_client()
+        .get(format!("{GB_LEGACY_API}/{GB_LEGACY_DATA}"))
+        .query(&[
+            ("itemtype", "Mod".to_owned()),
+            ("itemid", mod_id.to_string()),
+            ("fields", "name,Files().aFiles(),text".to_owned()),
+        ]);
+    let text = req.send().await?.text().await?;
+    match sonic_rs::from_str::<(String, GbLegacyFiles, String)>(&text) {
+        Ok((name, files, text)) => {
+            let mut files: Vec<GbModDownload> = match files {
+                GbLegacyFiles::Files(files) => files.into_values().map(|f| f.into()).collect(),
+                GbLegacyFiles::Empty(_) => vec![],
+            };
+            files.sort_by_key(|f| f.date_added);
+            Ok(GbMod {
+                name,
+                files: Some(files),
+                text: Some(text),
+                submitter: None,
+            })
+        }
+        Err(e) => {
+            eprintln!("{}", text);
+            Err(e.into())
+        }
+    }
+}
+
+pub fn missing_image_buf() -> SharedPixelBuffer<Rgba8Pixel> {
+    let bytes = include_bytes!("../ui/assets/missing-image.png");
+    let image = image::load_from_memory(bytes).unwrap();
+    let image = image
+        .resize(
+            440 as u32,
+            248 as u32,
+            image::imageops::FilterType::Triangle,
+        )
+        .into_rgba8();
+    SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(image.as_raw(), image.width(), image.height())
+}
+
+pub fn _missing_image() -> slint::Image {
+    slint::Image::from_rgba8(missing_image_buf())
+}
+
+/// Somewhere the details of a mod are shown, either their own window or the panel next to the
+/// search results
+pub trait DetailsView: ComponentHandle + 'static {
+    fn details_data(&self) -> GbPreviewData;
+    fn set_details_data(&self, data: GbPreviewData);
+    fn details_files(&self) -> ModelRc<Download>;
+    fn set_details_files(&self, files: ModelRc<Download>);
+    fn set_details_description(&self, description: SharedString);
+    fn set_details_files_error(&self, error: SharedString);
+    fn details_dependencies(&self) -> ModelRc<i32>;
+    fn set_details_dependencies(&self, dependencies: ModelRc<i32>);
+    fn details_modpacks(&self) -> ModelRc<SharedString>;
+    fn set_details_modpacks(&self, modpacks: ModelRc<SharedString>);
+    fn details_target_pack(&self) -> i32;
+    fn on_details_download(&self, f: impl Fn(Download) + 'static);
+    fn invoke_details_download(&self, download: Download);
+    fn on_details_refresh(&self, f: impl Fn() + 'static);
+    fn on_details_move_to_front(&self, f: impl Fn(i32) + 'static);
+    fn on_details_cancel_download(&self, f: impl Fn(i32) + 'static);
+    fn set_details_install_default(&self, install: bool);
+}
+
+impl DetailsView for GbDetailsWindow {
+    fn details_data(&self) -> GbPreviewData {
+        self.get_data()
+    }
+    fn set_details_data(&self, data: GbPreviewData) {
+        self.set_data(data)
+    }
+    fn details_files(&self) -> ModelRc<Download> {
+        self.get_files()
+    }
+    fn set_details_files(&self, files: ModelRc<Download>) {
+        self.set_files(files)
+    }
+    fn set_details_description(&self, description: SharedString) {
+        self.set_description(description)
+    }
+    fn set_details_files_error(&self, error: SharedString) {
+        self.set_files_error(error)
+    }
+    fn details_dependencies(&self) -> ModelRc<i32> {
+        self.get_dependencies()
+    }
+    fn set_details_dependencies(&self, dependencies: ModelRc<i32>) {
+        self.set_dependencies(dependencies)
+    }
+    fn details_modpacks(&self) -> ModelRc<SharedString> {
+        self.get_modpacks()
+    }
+    fn set_details_modpacks(&self, modpacks: ModelRc<SharedString>) {
+        self.set_modpacks(modpacks)
+    }
+    fn details_target_pack(&self) -> i32 {
+        self.get_target_pack_idx()
+    }
+    fn on_details_download(&self, f: impl Fn(Download) + 'static) {
+        self.global::<GameBananaLogic>().on_download(f)
+    }
+    fn invoke_details_download(&self, download: Download) {
+        self.global::<GameBananaLogic>().invoke_download(download)
+    }
+    fn on_details_refresh(&self, f: impl Fn() + 'static) {
+        self.on_refresh(f)
+    }
+    fn on_details_move_to_front(&self, f: impl Fn(i32) + 'static) {
+        self.global::<GameBananaLogic>()
+            .on_move_download_to_front(f)
+    }
+    fn on_details_cancel_download(&self, f: impl Fn(i32) + 'static) {
+        self.global::<GameBananaLogic>().on_cancel_download(f)
+    }
+    fn set_details_install_default(&self, install: bool) {
+        self.set_install_default(install)
+    }
+}
+
+impl DetailsView for App {
+    fn details_data(&self) -> GbPreviewData {
+        self.get_deets_data()
+    }
+    fn set_details_data(&self, data: GbPreviewData) {
+        self.set_deets_data(data)
+    }
+    fn details_files(&self) -> ModelRc<Download> {
+        self.get_deets_files()
+    }
+    fn set_details_files(&self, files: ModelRc<Download>) {
+        self.set_deets_files(files)
+    }
+    fn set_details_description(&self, description: SharedString) {
+        self.set_deets_description(description)
+    }
+    fn set_details_files_error(&self, error: SharedString) {
+        self.set_deets_files_error(error)
+    }
+    fn details_dependencies(&self) -> ModelRc<i32> {
+        self.get_deets_dependencies()
+    }
+    fn set_details_dependencies(&self, dependencies: ModelRc<i32>) {
+        self.set_deets_dependencies(dependencies)
+    }
+    fn details_modpacks(&self) -> ModelRc<SharedString> {
+        self.get_deets_modpacks()
+    }
+    fn set_details_modpacks(&self, modpacks: ModelRc<SharedString>) {
+        self.set_deets_modpacks(modpacks)
+    }
+    fn details_target_pack(&self) -> i32 {
+        self.get_deets_target_pack_idx()
+    }
+    fn on_details_download(&self, f: impl Fn(Download) + 'static) {
+        self.global::<GameBananaLogic>().on_download(f)
+    }
+    fn invoke_details_download(&self, download: Download) {
+        self.global::<GameBananaLogic>().invoke_download(download)
+    }
+    fn on_details_refresh(&self, f: impl Fn() + 'static) {
+        self.on_deets_refresh(f)
+    }
+    fn on_details_move_to_front(&self, f: impl Fn(i32) + 'static) {
+        self.global::<GameBananaLogic>()
+            .on_move_download_to_front(f)
+    }
+    fn on_details_cancel_download(&self, f: impl Fn(i32) + 'static) {
+        self.global::<GameBananaLogic>().on_cancel_download(f)
+    }
+    fn set_details_install_default(&self, install: bool) {
+        self.set_deets_install_default(install)
+    }
+}
+
+/// Shows the details of a mod in the main window or a new window depending on the settings.
+/// One-click installs pass the mod's files and the id of the file to start downloading
+pub fn open_details(
+    item: GbPreviewData,
+    weak: Weak<App>,
+    dark_rx: broadcast::Receiver<ColorScheme>,
+    start: Option<(Vec<GbModDownload>, i32)>,
+) {
+    let item_id = item.id;
+    if !R4D_CFG.try_lock().is_ok_and(|cfg| cfg.embedded_details) {
+        // a mod that is already open gets its window back instead of a second one
+        let open = DETAILS_WINDOWS.with_borrow(|windows| {
+            windows
+                .iter()
+                .find(|w| w.id == item_id)
+                .map(|w| w.window.clone_strong())
+        });
+        let deets = match open {
+            Some(deets) => deets,
+            None => create_deets_window(item, weak, dark_rx),
+        };
+        if let Some((files, file_id)) = start {
+            start_download(&deets, &files, file_id, item_id);
+        }
+        deets.show().unwrap();
+        return;
+    }
+    let Some(ui) = weak.upgrade() else {
+        return;
+    };
+    // the panel only ever shows one mod, whatever was in it is replaced
+    ui.set_deets_files(ModelRc::default());
+    ui.set_deets_description(SharedString::new());
+    ui.set_deets_dependencies(ModelRc::default());
+    ui.set_deets_target_pack_idx(0);
+    let tasks = setup_details(&ui, item, weak.clone());
+    // anything still loading for the mod shown before isn't needed anymore
+    PANEL_TASKS.with_borrow_mut(|old| {
+        for task in old.iter() {
+            task.abort();
+        }
+        *old = tasks;
+    });
+    if let Some((files, file_id)) = start {
+        start_download(&ui, &files, file_id, item_id);
+    }
+    ui.set_deets_open(true);
+    ui.set_current_tab("search".into());
+}
+
+/// Lists the files with the one-click file already marked as started and downloads it
+fn start_download<T: DetailsView>(
+    view: &T,
+    files: &Vec<GbModDownload>,
+    file_id: i32,
+    item_id: i32,
+) {
+    let install = install_downloads();
+    let model: VecModel<Download> = VecModel::default();
+    for file in files {
+        let mut f: Download = file.clone().into();
+        if f.id == file_id {
+            f.inprogress = true;
+            f.install = install;
+        }
+        model.push(f);
+    }
+    view.set_details_files(ModelRc::new(model));
+    match files.iter().find(|f| f.id == file_id) {
+        Some(file) => view.invoke_details_download(file.clone().into()),
+        None => open_error_window(format!(
+            "File {file_id} is not one of the files of mod {item_id}"
+        )),
+    }
+}
+
+pub fn create_deets_window(
+    item: GbPreviewData,
+    weak: Weak<App>,
     dark_rx: broadcast::Receiver<ColorScheme>,
 ) -> GbDetailsWindow {
     let deets = GbDetailsWindow::new().unwrap();
     if let Ok(cfg) = R4D_CFG.try_lock() {
-        deets.invoke_set_color_scheme(if cfg.dark_mode {
-            ColorScheme::Dark
-        } else {
-            ColorScheme::Light
-        });
+        deets.invoke_set_color_scheme(cfg.color_scheme());
     }
-    let item_id = item.id.clone();
 
     deets
         .global::<HyperLink>()
-        .on_open_hyperlink(|link| match open::that(link.to_string()) {
-            Ok(_) => {}
-            Err(e) => eprintln!("{e}"),
+        .on_open_hyperlink(|link| open_hyperlink(&link));
+
+    let id = item.id;
+    let mut tasks = setup_details(&deets, item, weak);
+
+    let deets_weak = deets.as_weak();
+    let mut scheme_rx = dark_rx.resubscribe();
+    let scheme_changer = tokio::spawn(async move {
+        while let Ok(scheme) = scheme_rx.recv().await {
+            let _ = deets_weak.upgrade_in_event_loop(move |deets| {
+                deets.invoke_set_color_scheme(scheme);
+            });
+        }
+    });
+
+    tasks.push(scheme_changer.abort_handle());
+
+    let key = NEXT_DETAILS_KEY.fetch_add(1, Ordering::Relaxed);
+    deets.window().on_close_requested(move || {
+        // the handle is dropped once the close has been handled
+        let _ = slint::invoke_from_event_loop(move || close_details_window(key));
+        slint::CloseRequestResponse::HideWindow
+    });
+    let oldest = DETAILS_WINDOWS.with_borrow_mut(|windows| {
+        windows.push(OpenDetails {
+            key,
+            id,
+            window: deets.clone_strong(),
+            tasks,
         });
+        (windows.len() > MAX_DETAILS_WINDOWS).then(|| windows[0].key)
+    });
+    if let Some(oldest) = oldest {
+        close_details_window(oldest);
+    }
+    deets
+}
+
+/// Hides a details window, stops whatever was still loading for it and drops its handle
+fn close_details_window(key: usize) {
+    let details = DETAILS_WINDOWS.with_borrow_mut(|windows| {
+        let idx = windows.iter().position(|w| w.key == key)?;
+        Some(windows.remove(idx))
+    });
+    if let Some(details) = details {
+        for task in &details.tasks {
+            task.abort();
+        }
+        let _ = details.window.hide();
+    }
+}
+
+/// Fills in the files and description of the mod shown in the details view
+fn load_details_info<T: DetailsView>(deets: &T, item_id: i32, weak: Weak<App>) -> AbortHandle {
+    let deets_weak = deets.as_weak();
+    let task = tokio::spawn(async move {
+        match cached_mod_info(item_id).await {
+            Ok(module) => {
+                let files = module.files.clone().unwrap_or_default();
+                set_result_file_info(weak, item_id, &files);
+                let _ = deets_weak.upgrade_in_event_loop(move |deets| {
+                    if deets.details_data().id != item_id {
+                        return;
+                    }
+                    // keep the state of downloads that were started before the info arrived,
+                    // one-click starts its file as soon as the window opens
+                    let current = deets.details_files();
+                    let vecmod: VecModel<Download> = VecModel::default();
+                    for file in module.files.unwrap_or(vec![]) {
+                        let mut download: Download = file.into();
+                        if let Some(old) = current.iter().find(|d| d.id == download.id) {
+                            download.inprogress = old.inprogress;
+                            download.progress = old.progress;
+                            download.failed = old.failed;
+                        }
+                        vecmod.push(download);
+                    }
+                    deets.set_details_files(ModelRc::new(vecmod));
+                    deets.set_details_files_error("".into());
+                    let text = module.text.unwrap_or_default();
+                    let dependencies = parse_gb_mod_links(&text, item_id);
+                    deets.set_details_dependencies(ModelRc::new(VecModel::from(dependencies)));
+                    deets.set_details_description(text.replace("<br>", "\n").into());
+                });
+            }
+            Err(e) => {
+                let error = format!("Failed to load the files: {e}");
+                let _ = deets_weak.upgrade_in_event_loop(move |deets| {
+                    if deets.details_data().id == item_id {
+                        deets.set_details_files_error(error.into());
+                    }
+                });
+                open_error_window(e.to_string());
+            }
+        }
+    });
+    task.abort_handle()
+}
+
+/// Fills in the details of a mod and handles its downloads. Updates that arrive after the view
+/// moved on to another mod are dropped, the panel in the main window gets reused. Returns the
+/// tasks loading the details so they can be stopped when the view closes, downloads keep going
+fn setup_details<T: DetailsView>(
+    deets: &T,
+    item: GbPreviewData,
+    weak: Weak<App>,
+) -> Vec<AbortHandle> {
+    let item_id = item.id.clone();
+    let mut tasks = vec![];
 
     let deets_weak = deets.as_weak();
     if !item.image_loaded && !item.image_url.is_empty() {
         let url = item.image_url.to_string();
         println!("Loading image for preview window: {}", url);
-        tokio::spawn(async move {
-            let buf = match get_image(url).await {
+        let task = tokio::spawn(async move {
+            let buf = match get_image(url, thumb_dimensions(2)).await {
                 Ok(buf) => buf,
                 Err(e) => {
                     eprintln!("{e}");
@@ -535,150 +1812,183 @@ pub fn create_deets_window(
             };
             println!("Got image");
             let _ = deets_weak.upgrade_in_event_loop(move |deets| {
-                let mut data = deets.get_data();
+                let mut data = deets.details_data();
+                if data.id != item_id {
+                    return;
+                }
                 data.image = slint::Image::from_rgba8(buf);
                 data.image_loaded = true;
-                deets.set_data(data);
+                deets.set_details_data(data);
             });
         });
+        tasks.push(task.abort_handle());
     }
-    deets.set_data(item);
-    let deets_weak = deets.as_weak();
+    deets.set_details_data(item);
+    deets.set_details_files_error("".into());
+    deets.set_details_install_default(install_downloads());
+    if let Ok(packs) = MOD_PACKS.try_lock() {
+        let mut names: Vec<SharedString> = packs.keys().map(|p| p.into()).collect();
+        names.sort_by_key(|p| p.to_lowercase());
+        names.insert(0, "None".into());
+        deets.set_details_modpacks(ModelRc::new(VecModel::from(names)));
+    }
+    tasks.push(load_details_info(deets, item_id, weak.clone()));
 
-    tokio::spawn(async move {
-        match fetch_mod_info(item_id).await {
-            Ok(module) => {
-                let _ = deets_weak.upgrade_in_event_loop(move |deets| {
-                    let vecmod: VecModel<Download> = VecModel::default();
-                    for file in module.files.unwrap_or(vec![]) {
-                        vecmod.push(file.into());
-                    }
-                    deets.set_files(ModelRc::new(vecmod));
-                    deets.set_description(
-                        module.text.unwrap_or_default().replace("<br>", "\n").into(),
-                    );
-                });
-            }
-            Err(e) => open_error_window(e.to_string()),
-        }
+    let refresh_weak = deets.as_weak();
+    let results_weak = weak.clone();
+    deets.on_details_refresh(move || {
+        let Some(deets) = refresh_weak.upgrade() else {
+            return;
+        };
+        invalidate_mod_info(item_id);
+        load_details_info(&deets, item_id, results_weak.clone());
+    });
+
+    deets.on_details_move_to_front(move |id| {
+        move_download_to_front(id);
+    });
+    deets.on_details_cancel_download(move |id| {
+        cancel_download(id);
     });
 
     let weak = weak.clone();
     let deets_weak = deets.as_weak();
-    deets
-        .global::<GameBananaLogic>()
-        .on_download(move |download| {
-            let weak = weak.clone();
-            println!("{}", download.url.to_string());
-            let deets = deets_weak.unwrap();
-            let model = deets.get_files();
-            let files = match model.as_any().downcast_ref::<VecModel<Download>>() {
-                Some(vec) => vec,
-                None => return,
-            };
-            if let Some(idx) = files.iter().position(|i| i.id == download.id) {
-                let deets_weak = deets_weak.clone();
-                let (tx, mut rx) = channel::<usize>(30000);
-                let row = idx.clone();
-                tokio::spawn(async move {
-                    let wait_time = tokio::time::Duration::from_millis(50);
-                    while !rx.is_closed() || !rx.is_empty() {
-                        if let Ok(len) = rx.try_recv() {
-                            let row = row.clone();
-                            let _ = deets_weak.upgrade_in_event_loop(move |deets| {
-                                if let Some(mut dl) = deets.get_files().row_data(row) {
-                                    dl.progress += len as i32;
-                                    deets.get_files().set_row_data(row, dl);
+    deets.on_details_download(move |download| {
+        let weak = weak.clone();
+        println!("{}", download.url.to_string());
+        let deets = deets_weak.unwrap();
+        let model = deets.details_files();
+        let files = match model.as_any().downcast_ref::<VecModel<Download>>() {
+            Some(vec) => vec,
+            None => return,
+        };
+        if let Some(idx) = files.iter().position(|i| i.id == download.id) {
+            let mut download = download;
+            download.queued = download_queue_full();
+            files.set_row_data(idx, download.clone());
+            let pack_weak = deets_weak.clone();
+            let failed_weak = deets_weak.clone();
+            let target_pack = selected_target_pack(&deets);
+            let deets_weak = deets_weak.clone();
+            let (tx, mut rx) = channel::<DownloadProgress>(30000);
+            let row = idx.clone();
+            tokio::spawn(async move {
+                let wait_time = tokio::time::Duration::from_millis(50);
+                while !rx.is_closed() || !rx.is_empty() {
+                    if let Ok(update) = rx.try_recv() {
+                        let row = row.clone();
+                        let _ = deets_weak.upgrade_in_event_loop(move |deets| {
+                            if deets.details_data().id != item_id {
+                                return;
+                            }
+                            if let Some(mut dl) = deets.details_files().row_data(row) {
+                                dl.queued = false;
+                                match update {
+                                    DownloadProgress::Started => {}
+                                    DownloadProgress::Received(len) => {
+                                        dl.progress += len as i32;
+                                        dl.status = Default::default();
+                                    }
+                                    DownloadProgress::Retrying { attempt, retries } => {
+                                        dl.progress = 0;
+                                        dl.status =
+                                            format!("Retrying ({attempt}/{retries})").into();
+                                    }
                                 }
-                            });
-                        } else {
-                            sleep(wait_time).await;
-                        }
+                                deets.details_files().set_row_data(row, dl);
+                            }
+                        });
+                    } else {
+                        sleep(wait_time).await;
                     }
-                });
+                }
+            });
 
-                tokio::spawn(async move {
-                    let req = reqwest_client().get(download.url.to_string()).send();
-                    let res = match req.await {
-                        Ok(res) => match res.error_for_status() {
-                            Ok(res) => res,
-                            Err(e) => {
+            let dependencies: Vec<i32> = match R4D_CFG.try_lock() {
+                Ok(cfg) if cfg.suggest_dependencies => {
+                    deets.details_dependencies().iter().collect()
+                }
+                _ => vec![],
+            };
+            let provenance = ModProvenance {
+                gb_id: item_id,
+                file_id: download.id,
+                file: download.name.to_string(),
+            };
+            let install = download.install;
+            tokio::spawn(async move {
+                let buf =
+                    match download_to_temp(download, Some(tx)).await {
+                        Ok(buf) => buf,
+                        Err(e) => {
+                            let cancelled = is_cancelled(e.as_ref());
+                            update_download_row(&failed_weak, item_id, idx, move |dl| {
+                                match cancelled {
+                                    true => reset_download(dl),
+                                    false => dl.failed = true,
+                                }
+                            });
+                            if !cancelled {
                                 open_error_window(e.to_string());
-                                return;
                             }
-                        },
-                        Err(e) => {
-                            open_error_window(e.to_string());
                             return;
                         }
                     };
-                    println!("{}", res.status());
-                    let mut stream = res.bytes_stream();
-                    let mut bytes = vec![];
-                    let tx = tx;
-                    while let Some(chunk) = stream.next().await {
-                        match chunk {
-                            Ok(chunk) => {
-                                let _ = tx.try_send(chunk.len());
-                                bytes.push(chunk);
-                            }
-                            Err(e) => {
-                                open_error_window(e.to_string());
-                                return;
-                            }
-                        }
-                    }
-                    println!("Done, len: {}", bytes.len());
-                    if let Some(dir) = get_temp_folder() {
-                        let mut buf = PathBuf::from(dir);
-                        buf.push(download.name.to_string());
-                        match File::create(buf.clone()) {
-                            Ok(mut file) => {
-                                for chunk in bytes {
-                                    if let Err(e) = file.write_all(&chunk) {
-                                        open_error_window(e.to_string());
-                                        return;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                open_error_window(e.to_string());
-                                return;
-                            }
+                if !install {
+                    show_downloaded_archive(buf).await;
+                    return;
+                }
+                match install_archive(buf, provenance, ExistingMod::Ask).await {
+                    Ok(new_mods) => {
+                        match set_mods_table(&get_mods(), weak.clone()) {
+                            Ok(_) => {}
+                            Err(e) => eprintln!("{e}"),
                         }
-                        match unpack_mod_path(buf).await {
-                            Ok(_) => {
-                                if load_mods().is_ok() {
-                                    match set_mods_table(&get_mods(), weak.clone()) {
-                                        Ok(_) => {}
-                                        Err(e) => eprintln!("{e}"),
-                                    }
+                        let weak = weak.clone();
+                        let _ = slint::invoke_from_event_loop(move || {
+                            // the pack can still be changed while the download is running
+                            let target = match pack_weak.upgrade() {
+                                Some(deets) if deets.details_data().id == item_id => {
+                                    selected_target_pack(&deets)
                                 }
+                                _ => target_pack,
+                            };
+                            if let Some(pack) = target {
+                                add_new_mods_to_pack(pack, new_mods, weak);
                             }
-                            Err(e) => {
-                                open_error_window(e.to_string());
-                            }
-                        }
+                        });
                     }
-                });
-            }
-        });
-
-    let deets_weak = deets.as_weak();
-    let mut scheme_rx = dark_rx.resubscribe();
-    let scheme_changer = tokio::spawn(async move {
-        while let Ok(scheme) = scheme_rx.recv().await {
-            let _ = deets_weak.upgrade_in_event_loop(move |deets| {
-                deets.invoke_set_color_scheme(scheme);
+                    Err(e) => {
+                        open_error_window(e.to_string());
+                    }
+                }
+                if !dependencies.is_empty() {
+                    install_dependencies(dependencies, weak).await;
+                }
             });
         }
     });
+    tasks
+}
 
-    deets.window().on_close_requested(move || {
-        scheme_changer.abort();
-        slint::CloseRequestResponse::HideWindow
-    });
-    deets
+/// Whether downloads started from the details view or one-click are installed once they finish
+fn install_downloads() -> bool {
+    R4D_CFG.try_lock().map_or(true, |cfg| cfg.install_downloads)
+}
+
+/// Tells where an archive that was only downloaded ended up, it is left there to be installed later
+async fn show_downloaded_archive(archive: PathBuf) {
+    let msg = format!(
+        "The archive was saved to {}\n\nOpen its folder?",
+        archive.display()
+    );
+    if ask_confirmation("Download Finished".to_owned(), msg).await {
+        if let Some(dir) = archive.parent() {
+            if let Err(e) = open::that(dir) {
+                open_error_window(e.to_string());
+            }
+        }
+    }
 }
 
 pub async fn fetch_mod(id: i32) -> Result<GBSearch, Box<dyn Error + Send + Sync>> {
@@ -693,6 +2003,850 @@ pub async fn fetch_mod(id: i32) -> Result<GBSearch, Box<dyn Error + Send + Sync>
     }
 }
 
+fn total_file_size(files: &Vec<GbModDownload>) -> i32 {
+    let total: u64 = files.iter().map(|f| f.filesize as u64).sum();
+    min(total, i32::MAX as u64) as i32
+}
+
+/// Shows the number of files and their total size on the search result for the given mod
+fn set_result_file_info(weak: Weak<App>, id: i32, files: &Vec<GbModDownload>) {
+    let file_count = files.len() as i32;
+    let files_size = total_file_size(files);
+    let _ = weak.upgrade_in_event_loop(move |ui| {
+        let model = ui.get_s_results();
+        for i in 0..model.row_count() {
+            let mut row = model.row_data(i).unwrap();
+            if row.id == id {
+                row.has_files = file_count > 0;
+                row.file_count = file_count;
+                row.files_size = files_size;
+                model.set_row_data(i, row);
+                return;
+            }
+        }
+    });
+}
+
+/// Returns the pack picked in the details window that new mods should be added to, if any
+fn selected_target_pack<T: DetailsView>(deets: &T) -> Option<String> {
+    match deets.details_target_pack() {
+        idx if idx > 0 => deets
+            .details_modpacks()
+            .row_data(idx as usize)
+            .map(|p| p.to_string()),
+        _ => None,
+    }
+}
+
+/// Changes a file row of the details the download was started from, as long as they still show
+/// the same mod
+fn update_download_row<T: DetailsView>(
+    weak: &Weak<T>,
+    item_id: i32,
+    idx: usize,
+    update: impl FnOnce(&mut Download) + Send + 'static,
+) {
+    let _ = weak.upgrade_in_event_loop(move |deets| {
+        if deets.details_data().id != item_id {
+            return;
+        }
+        if let Some(mut dl) = deets.details_files().row_data(idx) {
+            update(&mut dl);
+            deets.details_files().set_row_data(idx, dl);
+        }
+    });
+}
+
+/// Puts a file row back to how it was before the download was started
+fn reset_download(dl: &mut Download) {
+    dl.inprogress = false;
+    dl.queued = false;
+    dl.progress = 0;
+    dl.status = Default::default();
+}
+
+/// Asks before downloading a file GameBanana found executables in, unless turned off in the settings
+async fn confirm_executables(download: &Download) -> bool {
+    let warn = R4D_CFG.try_lock().map_or(true, |cfg| cfg.warn_executables);
+    if !download.contains_exe || !warn {
+        return true;
+    }
+    let scan = |res: &SharedString| match res.is_empty() {
+        true => "not scanned".to_owned(),
+        false => res.to_string(),
+    };
+    let msg = format!(
+        "{} contains executable files, only download it if you trust the author.\n\nClamAV: {}\nAvast: {}\n\nDownload it anyway?",
+        download.name,
+        scan(&download.clam_av_result),
+        scan(&download.avast_av_result)
+    );
+    ask_confirmation("Executable Files".to_owned(), msg).await
+}
+
+fn add_new_mods_to_pack(pack: String, mods: Vec<DivaMod>, weak: Weak<App>) {
+    if mods.is_empty() {
+        return;
+    }
+    match add_mods_to_pack(&pack, mods) {
+        Ok(_) => {
+            if let Some(ui) = weak.upgrade() {
+                if ui.get_active_pack().to_string() == pack {
+                    ui.global::<ModpackLogic>()
+                        .invoke_change_modpack(pack.into());
+                }
+            }
+        }
+        Err(e) => open_error_window(format!("Unable to add mod to {pack}: \n{e}")),
+    }
+}
+
+/// Downloads a file into the temp folder, the size of each received chunk is sent over `progress`.
+/// Files with executables are only downloaded once the user confirmed it
+pub async fn download_to_temp(
+    download: Download,
+    progress: Option<Sender<DownloadProgress>>,
+) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    if !confirm_executables(&download).await {
+        return Err(DownloadCancelled.into());
+    }
+    let id = download.id;
+    let part = part_file(&download)?;
+    let task = tokio::spawn(async move {
+        let _slot = acquire_download_slot(id, download.priority).await;
+        if let Some(progress) = &progress {
+            let _ = progress.send(DownloadProgress::Started).await;
+        }
+        if let Ok(mut active) = ACTIVE_DOWNLOADS.lock() {
+            active.insert(id, download.clone().into());
+        }
+        let retries = R4D_CFG
+            .try_lock()
+            .map_or(DEFAULT_DOWNLOAD_RETRIES, |cfg| cfg.download_retries);
+        let mut attempt = 0;
+        loop {
+            match download_file(download.clone(), progress.clone()).await {
+                Err(e) if attempt < retries && is_transient(e.as_ref()) => {
+                    attempt += 1;
+                    eprintln!(
+                        "Download of {} failed, retrying ({attempt}/{retries}): {e}",
+                        download.name
+                    );
+                    if let Some(progress) = &progress {
+                        let _ = progress
+                            .send(DownloadProgress::Retrying { attempt, retries })
+                            .await;
+                    }
+                    if let Ok(mut active) = ACTIVE_DOWNLOADS.lock() {
+                        if let Some(status) = active.get_mut(&id) {
+                            status.progress = 0;
+                        }
+                    }
+                    // waits 1s, 2s, 4s and so on
+                    sleep(Duration::from_secs(1 << (attempt - 1).min(6))).await;
+                }
+                res => return res,
+            }
+        }
+    });
+    if let Ok(mut tasks) = DOWNLOAD_TASKS.lock() {
+        tasks.insert(id, task.abort_handle());
+    }
+    let res = task.await;
+    if let Ok(mut tasks) = DOWNLOAD_TASKS.lock() {
+        tasks.remove(&id);
+    }
+    if let Ok(mut active) = ACTIVE_DOWNLOADS.lock() {
+        active.remove(&id);
+    }
+    match res {
+        Ok(res) => res,
+        Err(e) if e.is_cancelled() => {
+            // a cancelled download starts over instead of resuming
+            let _ = std::fs::remove_file(&part);
+            Err(DownloadCancelled.into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Stops a queued or running download, downloads that already finished are left alone
+pub fn cancel_download(id: i32) {
+    if let Some(task) = DOWNLOAD_TASKS.lock().ok().and_then(|mut t| t.remove(&id)) {
+        task.abort();
+    }
+}
+
+/// Network errors and server errors are worth retrying, anything the server refused like a
+/// missing file or a file that doesn't match its checksum isn't
+fn is_transient(e: &(dyn Error + Send + Sync)) -> bool {
+    let Some(e) = e.downcast_ref::<reqwest::Error>() else {
+        return false;
+    };
+    match e.status() {
+        Some(status) => status.is_server_error(),
+        None => e.is_connect() || e.is_timeout() || e.is_request() || e.is_body(),
+    }
+}
+
+/// Error of a download that was stopped with [`cancel_download`] or declined because of executables,
+/// kept apart from io errors so an interrupted read or write still shows up as a failure
+#[derive(Debug)]
+pub struct DownloadCancelled;
+
+impl std::fmt::Display for DownloadCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The download was cancelled")
+    }
+}
+
+impl Error for DownloadCancelled {}
+
+/// Whether a download failed because it was cancelled rather than because of an error
+pub fn is_cancelled(e: &(dyn Error + Send + Sync)) -> bool {
+    e.is::<DownloadCancelled>()
+}
+
+/// The file a download is written to until it's complete
+fn part_file(download: &Download) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let mut buf = PathBuf::from(get_temp_folder().ok_or("Unable to get temp folder")?);
+    buf.push(format!("{}.part", download.name));
+    Ok(buf)
+}
+
+/// Returns the downloads that are currently running
+pub fn get_active_downloads() -> Vec<DownloadStatus> {
+    let mut downloads: Vec<DownloadStatus> = match ACTIVE_DOWNLOADS.lock() {
+        Ok(active) => active.values().cloned().collect(),
+        Err(_) => vec![],
+    };
+    downloads.sort_by_key(|d| d.id);
+    downloads
+}
+
+/// Whether GameBanana answered with a web page instead of a file, this is either the NSFW consent
+/// page or something like a maintenance page, see [`is_nsfw_gate_page`]
+fn is_html_page(res: &reqwest::Response) -> bool {
+    res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|t| t.to_str().ok())
+        .is_some_and(|t| t.starts_with("text/html"))
+}
+
+/// Whether a page sent instead of a file is the NSFW consent page
+fn is_nsfw_gate_page(page: &str) -> bool {
+    let title = page_title(page).to_lowercase();
+    GB_NSFW_GATE_MARKERS.iter().any(|m| title.contains(m))
+}
+
+/// The start of a page's title, used to say what GameBanana sent instead of a file
+fn page_title(page: &str) -> String {
+    // ascii only so the offsets still fit the original page
+    let lower = page.to_ascii_lowercase();
+    let title = lower
+        .find("<title>")
+        .map(|start| start + "<title>".len())
+        .and_then(|start| Some((start, start + lower[start..].find("</title>")?)))
+        .map_or("", |(start, end)| page[start..end].trim());
+    title.chars().take(80).collect()
+}
+
+/// Requests a file starting at `offset`. NSFW files GameBanana only serves after its consent page
+/// are reported as such, they have to be downloaded from the site
+async fn request_download(
+    download: &Download,
+    offset: u64,
+) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+    let mut req = reqwest_client().get(download.url.to_string());
+    if offset > 0 {
+        req = req.header(RANGE, format!("bytes={offset}-"));
+    }
+    let res = req.send().await?;
+    if offset > 0 && res.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // the file changed since the part was downloaded
+        return Box::pin(request_download(download, 0)).await;
+    }
+    let res = res.error_for_status()?;
+    if is_html_page(&res) {
+        let page = res.text().await?;
+        if !is_nsfw_gate_page(&page) {
+            return Err(format!(
+                "GameBanana sent a web page instead of {} ({}), the site may be down for maintenance",
+                download.name,
+                page_title(&page)
+            )
+            .into());
+        }
+        return Err(format!(
+            "{} is marked as NSFW, GameBanana only serves it after accepting its content warning. Download it from the mod's page in a browser instead",
+            download.name
+        )
+        .into());
+    }
+    Ok(res)
+}
+
+/// Downloads into a `.part` file next to the final one, a part left by an interrupted download is
+/// continued with a range request unless the server sends the whole file again
+async fn download_file(
+    download: Download,
+    progress: Option<Sender<DownloadProgress>>,
+) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let part = part_file(&download)?;
+    let buf = part.with_file_name(download.name.to_string());
+    let offset = std::fs::metadata(&part).map_or(0, |m| m.len());
+    let res = request_download(&download, offset).await?;
+    println!("{}", res.status());
+    let resumed = offset > 0 && res.status() == StatusCode::PARTIAL_CONTENT;
+    let mut file = if resumed {
+        println!("Resuming {} at {offset} bytes", download.name);
+        report_download_progress(&download, &progress, offset as usize);
+        OpenOptions::new().append(true).open(&part)?
+    } else {
+        File::create(&part)?
+    };
+    let mut stream = res.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk)?;
+        report_download_progress(&download, &progress, chunk.len());
+    }
+    drop(file);
+    println!("Done, len: {}", std::fs::metadata(&part)?.len());
+    if let Err(e) = verify_md5(&download, file_md5(&part)?) {
+        let _ = std::fs::remove_file(&part);
+        return Err(e.into());
+    }
+    std::fs::rename(&part, &buf)?;
+    Ok(buf)
+}
+
+fn report_download_progress(
+    download: &Download,
+    progress: &Option<Sender<DownloadProgress>>,
+    len: usize,
+) {
+    if let Some(tx) = progress {
+        let _ = tx.try_send(DownloadProgress::Received(len));
+    }
+    if let Ok(mut active) = ACTIVE_DOWNLOADS.lock() {
+        if let Some(status) = active.get_mut(&download.id) {
+            status.progress += len as i32;
+        }
+    }
+}
+
+fn file_md5(path: &PathBuf) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        let len = file.read(&mut chunk)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&chunk[..len]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Compares the hash of a downloaded file to the checksum GameBanana lists for it
+fn verify_md5(download: &Download, actual: String) -> Result<(), String> {
+    let expected = download.md5.trim().to_lowercase();
+    if expected.is_empty() {
+        let allowed = R4D_CFG
+            .try_lock()
+            .map_or(true, |cfg| cfg.allow_unverified_downloads);
+        if allowed {
+            return Ok(());
+        }
+        return Err(format!(
+            "{} has no checksum on GameBanana, turn on installing files without a checksum in the settings to download it",
+            download.name
+        ));
+    }
+    if expected != actual {
+        return Err(format!(
+            "{} is corrupted, its checksum doesn't match GameBanana's\nExpected: {expected}\nGot: {actual}",
+            download.name
+        ));
+    }
+    Ok(())
+}
+
+/// Finds the ids of all GameBanana mods linked to in a mod's description
+pub fn parse_gb_mod_links(text: &str, own_id: i32) -> Vec<i32> {
+    let link_regex = Regex::new(r"gamebanana\.com/mods/([0-9]+)").unwrap();
+    let mut ids = vec![];
+    for link in link_regex.captures_iter(text) {
+        if let Ok(id) = link[1].parse::<i32>() {
+            if id != own_id && !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+/// Asks the user if they want to install the mods linked in a description, then installs the
+/// newest file of each one
+pub async fn install_dependencies(ids: Vec<i32>, weak: Weak<App>) {
+    let mut dependencies = vec![];
+    for id in ids {
+        match fetch_mod_info(id).await {
+            Ok(module) => {
+                if module.files.as_ref().is_some_and(|f| !f.is_empty()) {
+                    dependencies.push((id, module));
+                }
+            }
+            Err(e) => eprintln!("Unable to fetch linked mod {id}: {e}"),
+        }
+    }
+    if dependencies.is_empty() {
+        return;
+    }
+    let names: Vec<String> = dependencies
+        .iter()
+        .map(|(id, m)| format!("{} ({GB_DOMAIN}/mods/{id})", m.name))
+        .collect();
+    let msg = format!(
+        "This mod links to the following mods:\n{}\n\nInstall them as well?",
+        names.join("\n")
+    );
+    if !ask_confirmation("Install Linked Mods".to_owned(), msg).await {
+        return;
+    }
+    for (id, module) in dependencies {
+        let newest = module
+            .files
+            .unwrap_or_default()
+            .into_iter()
+            .max_by_key(|f| f.date_added);
+        if let Some(file) = newest {
+            let provenance = ModProvenance {
+                gb_id: id,
+                file_id: file.id,
+                file: file.file.clone(),
+            };
+            match download_to_temp(file.into(), None).await {
+                Ok(buf) => {
+                    if let Err(e) = install_archive(buf, provenance, ExistingMod::Ask).await {
+                        open_error_window(e.to_string());
+                    }
+                }
+                Err(e) if is_cancelled(e.as_ref()) => {}
+                Err(e) => open_error_window(e.to_string()),
+            }
+        }
+    }
+    if load_mods().is_ok() {
+        let _ = set_mods_table(&get_mods(), weak);
+    }
+}
+
+/// Lists every mod in a shared loadout for the user to confirm, then downloads them all and adds
+/// them to a pack named after the loadout
+pub async fn install_loadout(loadout: SharedLoadout, weak: Weak<App>) {
+    if !download_loadout_mods(&loadout).await {
+        return;
+    }
+    let mods = get_mods();
+    let _ = set_mods_table(&mods, weak.clone());
+    // mods that were already installed are included as well, in the order of the loadout
+    let pack_mods: Vec<DivaMod> = loadout
+        .mods
+        .iter()
+        .flat_map(|item| {
+            mods.iter()
+                .filter(|m| get_provenance(m).is_some_and(|p| p.gb_id == item.id))
+                .cloned()
+                .collect::<Vec<DivaMod>>()
+        })
+        .collect();
+    let pack = loadout.name;
+    let _ = weak.upgrade_in_event_loop(move |ui| {
+        ui.global::<ModpackLogic>()
+            .invoke_create_new_pack(pack.clone().into());
+        add_new_mods_to_pack(filenamify(pack), pack_mods, ui.as_weak());
+    });
+}
+
+/// Asks to download the mods of a shared loadout and installs them, false if the user declined or
+/// none of the mods could be found
+pub async fn download_loadout_mods(loadout: &SharedLoadout) -> bool {
+    let mut files = vec![];
+    for item in &loadout.mods {
+        match fetch_mod_info(item.id).await {
+            Ok(module) => {
+                let file = module
+                    .files
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find(|f| f.id == item.file);
+                match file {
+                    Some(file) => files.push((item.id, module.name, file)),
+                    None => eprintln!(
+                        "File {} is not one of the files of mod {}",
+                        item.file, item.id
+                    ),
+                }
+            }
+            Err(e) => eprintln!("Unable to fetch shared mod {}: {e}", item.id),
+        }
+    }
+    if files.is_empty() {
+        open_error_window(format!(
+            "None of the mods in {} could be found",
+            loadout.name
+        ));
+        return false;
+    }
+    let names: Vec<String> = files
+        .iter()
+        .map(|(id, name, file)| format!("{name} - {} ({GB_DOMAIN}/mods/{id})", file.file))
+        .collect();
+    let msg = format!(
+        "The shared loadout {} contains the following mods:\n{}\n\nDownload all of them?",
+        loadout.name,
+        names.join("\n")
+    );
+    if !ask_confirmation("Install Shared Loadout".to_owned(), msg).await {
+        return false;
+    }
+    for (id, _, file) in files {
+        let provenance = ModProvenance {
+            gb_id: id,
+            file_id: file.id,
+            file: file.file.clone(),
+        };
+        match download_to_temp(file.into(), None).await {
+            Ok(buf) => {
+                if let Err(e) = install_archive(buf, provenance, ExistingMod::Ask).await {
+                    open_error_window(e.to_string());
+                }
+            }
+            Err(e) if is_cancelled(e.as_ref()) => {}
+            Err(e) => open_error_window(e.to_string()),
+        }
+    }
+    true
+}
+
+/// Downloads the file every installed mod was installed from again and installs it over the
+/// current folder, mods that weren't downloaded through Rust4Diva are skipped
+pub async fn redownload_all(weak: Weak<App>) {
+    let mut targets = vec![];
+    let mut skipped = vec![];
+    let mut pinned = vec![];
+    let pinned_dirs = pinned_mods();
+    for module in get_mods() {
+        let name = module.config["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        if module.dir_name().is_some_and(|d| pinned_dirs.contains(&d)) {
+            pinned.push(name);
+            continue;
+        }
+        match get_provenance(&module) {
+            Some(provenance) => targets.push((name, provenance)),
+            None => skipped.push(name),
+        }
+    }
+    targets.sort_by_key(|(name, _)| name.to_lowercase());
+    skipped.sort_by_key(|name| name.to_lowercase());
+    pinned.sort_by_key(|name| name.to_lowercase());
+    if targets.is_empty() {
+        let msg = match pinned.is_empty() {
+            true => "None of the installed mods were downloaded from GameBanana",
+            false => {
+                "None of the installed mods that aren't pinned were downloaded from GameBanana"
+            }
+        };
+        open_error_window(msg.to_owned());
+        return;
+    }
+    let mut msg = format!(
+        "{} mods will be downloaded from GameBanana again and replace the installed files, the current files are backed up to the temp folder.",
+        targets.len()
+    );
+    if !skipped.is_empty() {
+        msg += &format!(
+            "\n\nThese mods weren't downloaded through Rust4Diva and will be skipped:\n{}",
+            skipped.join("\n")
+        );
+    }
+    if !pinned.is_empty() {
+        msg += &format!(
+            "\n\nThese mods are pinned and will be skipped:\n{}",
+            pinned.join("\n")
+        );
+    }
+    if !ask_confirmation("Re-download All Mods".to_owned(), msg).await {
+        return;
+    }
+
+    let total = targets.len() as i32;
+    let progress_weak = weak.clone();
+    let set_progress = move |done: i32| {
+        let _ = progress_weak.upgrade_in_event_loop(move |ui| {
+            ui.set_redownload_done(done);
+            ui.set_redownload_total(if done >= total { 0 } else { total });
+        });
+    };
+    set_progress(0);
+    let mut downloads =
+        futures_util::stream::iter(targets.into_iter().map(|(name, provenance)| async move {
+            let res = redownload_file(&provenance).await;
+            (name, provenance, res)
+        }))
+        .buffer_unordered(max_downloads());
+    let mut done = 0;
+    let mut failed = vec![];
+    // installs happen one at a time since they reload the mod list
+    while let Some((name, provenance, res)) = downloads.next().await {
+        let res = match res {
+            Ok(buf) => install_archive(buf, provenance, ExistingMod::Overwrite)
+                .await
+                .map(|_| ()),
+            Err(e) if is_cancelled(e.as_ref()) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            failed.push(format!("{name}: {e}"));
+        }
+        done += 1;
+        set_progress(done);
+    }
+    if load_mods().is_ok() {
+        let _ = set_mods_table(&get_mods(), weak.clone());
+    }
+    if !failed.is_empty() {
+        open_error_window(format!(
+            "Unable to re-download {} of {total} mods:\n{}",
+            failed.len(),
+            failed.join("\n")
+        ));
+    }
+}
+
+async fn redownload_file(
+    provenance: &ModProvenance,
+) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let file = fetch_source_file(provenance).await?;
+    download_to_temp(file.into(), None).await
+}
+
+/// Fetches the file a mod was installed from as it is on GameBanana now, download urls can change
+/// so they shouldn't be kept around
+pub async fn fetch_source_file(
+    provenance: &ModProvenance,
+) -> Result<GbModDownload, Box<dyn Error + Send + Sync>> {
+    let module = fetch_mod_info(provenance.gb_id).await?;
+    let file = module
+        .files
+        .unwrap_or_default()
+        .into_iter()
+        .find(|f| f.id == provenance.file_id)
+        .ok_or(format!(
+            "{} is no longer available on GameBanana",
+            provenance.file
+        ))?;
+    Ok(file)
+}
+
+/// Unpacks a downloaded archive, reloads the mod list and returns the mods that were added by it
+pub async fn install_archive(
+    buf: PathBuf,
+    provenance: ModProvenance,
+    existing: ExistingMod,
+) -> Result<Vec<DivaMod>, Box<dyn Error + Send + Sync>> {
+    let installed: Vec<String> = get_mods().iter().filter_map(|m| m.dir_name()).collect();
+    if let Err(e) = unpack_mod_path(buf.clone(), existing).await {
+        if !is_extraction_error(&e) {
+            return Err(e.into());
+        }
+        // formats libarchive can't read are left for the user to extract themselves
+        return Err(format!(
+            "Unable to extract {}: {e}\n\nThe downloaded file was kept at {}",
+            provenance.file,
+            buf.display()
+        )
+        .into());
+    }
+    load_mods()?;
+    let new_mods: Vec<DivaMod> = get_mods()
+        .into_iter()
+        .filter(|m| m.dir_name().is_some_and(|d| !installed.contains(&d)))
+        .collect();
+    if let Err(e) = record_provenance(&new_mods, provenance) {
+        eprintln!("Unable to save mod source: {e}");
+    }
+    Ok(new_mods)
+}
+
 pub fn get_mod_url(id: i32) -> String {
     format!("{GB_DOMAIN}/{GB_MOD_DATA}/{id}/ProfilePage")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dmm(url: &str) -> Option<GbDmmItem> {
+        parse_dmm_url(url.to_owned())
+    }
+
+    #[test]
+    fn dmm_url_file_id_comes_first() {
+        let item = dmm("divamodmanager:https://gamebanana.com/mmdl/1234567,Mod,456789").unwrap();
+        assert_eq!(item.file_id, 1234567);
+        assert_eq!(item.itemtype, "Mod");
+        assert_eq!(item.item_id, 456789);
+    }
+
+    #[test]
+    fn dmm_url_trailing_slash() {
+        let item = dmm("divamodmanager:https://gamebanana.com/mmdl/1,Sound,2/").unwrap();
+        assert_eq!((item.file_id, item.item_id), (1, 2));
+    }
+
+    #[test]
+    fn dmm_url_percent_encoded_commas() {
+        for url in [
+            "divamodmanager:https://gamebanana.com/mmdl/1187654%2CMod%2C512345",
+            "divamodmanager:https://gamebanana.com/mmdl/1187654%2cMod%2c512345",
+            "divamodmanager:https://gamebanana.com/mmdl/1187654%2CMod,512345/",
+        ] {
+            let item = dmm(url).unwrap();
+            assert_eq!((item.file_id, item.item_id), (1187654, 512345), "{url}");
+            assert_eq!(item.itemtype, "Mod");
+        }
+    }
+
+    #[test]
+    fn dmm_url_extra_segments_are_refused() {
+        assert!(
+            dmm("divamodmanager:https://gamebanana.com/mmdl/1234567,Mod,456789/extra").is_none()
+        );
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/1234567,Mod,456789,1").is_none());
+        assert!(
+            dmm("divamodmanager:https://gamebanana.com/mmdl/extra/1234567,Mod,456789").is_none()
+        );
+    }
+
+    #[test]
+    fn dmm_url_missing_parts_are_refused() {
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/").is_none());
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/1234567").is_none());
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/1234567,Mod").is_none());
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/1234567,,456789").is_none());
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/,Mod,456789").is_none());
+    }
+
+    #[test]
+    fn dmm_url_non_numeric_ids_are_refused() {
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/abc,Mod,456789").is_none());
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/1234567,Mod,45x789").is_none());
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/-1,Mod,456789").is_none());
+        // ids that don't fit an i32 can't be real GameBanana ids
+        assert!(dmm("divamodmanager:https://gamebanana.com/mmdl/99999999999,Mod,1").is_none());
+    }
+
+    fn download(id: i32, file: &str) -> GbModDownload {
+        GbModDownload {
+            id,
+            file: file.to_owned(),
+            filesize: 0,
+            description: String::new(),
+            date_added: 0,
+            download_count: 0,
+            md5_checksum: String::new(),
+            download_url: format!("https://gamebanana.com/dl/{id}"),
+            clam_av_result: String::new(),
+            avast_av_result: String::new(),
+            analysis_state: String::new(),
+            analysis_result: String::new(),
+            analysis_result_code: String::new(),
+            contains_exe: false,
+        }
+    }
+
+    #[test]
+    fn oneclick_url_picks_the_linked_file() {
+        // same shape as the 1-click install buttons on a GameBanana mod page, the mod has a file whose
+        // id matches the mod id so swapped ids would pick the wrong one
+        let item = dmm("divamodmanager:https://gamebanana.com/mmdl/1187654,Mod,512345").unwrap();
+        assert_eq!(item.item_id, 512345);
+        let files = vec![download(512345, "old.zip"), download(1187654, "new.7z")];
+        let file = oneclick_file(&files, &item).unwrap();
+        assert_eq!(file.file, "new.7z");
+        assert!(oneclick_file(&files[..1], &item).is_none());
+    }
+
+    #[test]
+    fn dmm_url_needs_the_prefix() {
+        assert!(dmm("https://gamebanana.com/mmdl/1234567,Mod,456789").is_none());
+        assert!(dmm("divamodmanager:http://gamebanana.com/mmdl/1234567,Mod,456789").is_none());
+        assert!(dmm("divamodmanager:https://example.com/mmdl/1234567,Mod,456789").is_none());
+        assert!(dmm("").is_none());
+    }
+
+    fn preview(id: i32, name: &str) -> GbPreviewData {
+        GbPreviewData {
+            id,
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlapping_pages_are_deduped() {
+        // the second page shifted by one while browsing so its first result was already shown
+        let shown: HashSet<i32> = [1, 2, 3].into_iter().collect();
+        let mut page = vec![
+            preview(3, "shifted"),
+            preview(4, "first"),
+            preview(4, "repeat"),
+            preview(5, "new"),
+        ];
+        dedupe_results(&mut page, &shown);
+        let ids: Vec<i32> = page.iter().map(|p| p.id).collect();
+        assert_eq!(ids, [4, 5]);
+        assert_eq!(page[0].name, "first");
+    }
+
+    #[test]
+    fn only_the_consent_page_counts_as_nsfw_gate() {
+        let gate = "<html><head><title>Content Warning</title></head><body>This file is marked NSFW. Continue?</body></html>";
+        assert!(is_nsfw_gate_page(gate));
+        let maintenance = "<html><head><title>GameBanana - Down for Maintenance</title></head><body>Back soon</body></html>";
+        assert!(!is_nsfw_gate_page(maintenance));
+        // the site's menus link to NSFW settings on every page
+        let menu = "<html><head><title>GameBanana</title></head><body><a>Show NSFW content</a></body></html>";
+        assert!(!is_nsfw_gate_page(menu));
+        assert!(!is_nsfw_gate_page(
+            "<html><body>502 Bad Gateway</body></html>"
+        ));
+    }
+
+    #[test]
+    fn page_titles() {
+        assert_eq!(
+            page_title("<html><TITLE> Down for Maintenance </TITLE></html>"),
+            "Down for Maintenance"
+        );
+        assert_eq!(page_title("<title>Ünïcödé</title>"), "Ünïcödé");
+        assert_eq!(page_title("<html>no title</html>"), "");
+        assert_eq!(page_title("<title>never closed"), "");
+    }
+
+    #[test]
+    fn only_cancels_count_as_cancelled() {
+        let cancelled: Box<dyn Error + Send + Sync> = DownloadCancelled.into();
+        assert!(is_cancelled(cancelled.as_ref()));
+        let interrupted: Box<dyn Error + Send + Sync> =
+            std::io::Error::new(std::io::ErrorKind::Interrupted, "interrupted").into();
+        assert!(!is_cancelled(interrupted.as_ref()));
+    }
+}
@@ -1,31 +1,44 @@
+use std::cell::Cell;
 use std::cmp::{max, min};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use std::{fs, io};
 
-use compress_tools::{list_archive_files, uncompress_archive, Ownership};
+use compress_tools::{
+    list_archive_files, uncompress_archive, ArchiveContents, ArchiveIteratorBuilder, Ownership,
+};
 use rfd::AsyncFileDialog;
 use serde::{Deserialize, Serialize};
 use slint::private_unstable_api::re_exports::ColorScheme;
-use slint::{ComponentHandle, EventLoopError, ModelRc, VecModel, Weak};
+use slint::{ComponentHandle, EventLoopError, Model, ModelRc, SharedString, VecModel, Weak};
 use toml_edit::{value, DocumentMut};
 
-use crate::config::{write_config, write_config_sync, write_dml_config};
-use crate::diva::{find_diva_folder, get_diva_folder, get_temp_folder, open_error_window};
-use crate::modpacks::{apply_mod_priority, save_modpack, save_modpack_sync, ModPackMod};
+use crate::config::{write_config, write_config_sync, write_dml_config, DivaConfig};
+use crate::diva::{
+    ask_choice, ask_confirmation, get_diva_folder, get_temp_folder, open_error_window, DialogAnswer,
+};
+use crate::modpacks::{
+    apply_mod_priority, load_all_packs, loaded_pack, refresh_order_changes, refresh_pack_mods,
+    save_modpack, save_modpack_sync, set_mod_enabled, snapshot_order, ExistingPack, ModPack,
+    ModPackMod,
+};
 use crate::slint_generatedApp::App;
-use crate::util::reqwest_client;
+use crate::undo::{record_action, ModAction};
+use crate::util::{fuzzy_score, reqwest_client, resolve_path_casing, walk_files};
 use crate::{
-    ConfirmDelete, DivaLogic, DivaModElement, EditModDialog, ModLogic, ModpackLogic, WindowLogic,
-    DIVA_DIR, MOD_PACKS,
+    ConfirmDelete, DivaLogic, DivaModElement, EditModDialog, ExportListDialog, ModListExport,
+    ModLogic, ModPickRow, ModState, ModpackLogic, ModsEmptyState, WindowLogic, DIVA_DIR, MOD_PACKS,
 };
-use crate::{DML_CFG, MODS, R4D_CFG};
+use crate::{DML_CFG, MODS, MODS_DIR, R4D_CFG};
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct DivaModConfig {
@@ -46,10 +59,87 @@ pub struct DivaModConfig {
     pub author: String,
 }
 
+/// Calculated mod folder sizes keyed by folder name, along with when the folder was last modified
+static MOD_SIZES: LazyLock<Mutex<HashMap<String, (u64, Option<SystemTime>)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static SIZING_MODS: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+/// column and direction the installed mods table is sorted by
+static MOD_SORT: Mutex<(i32, bool)> = Mutex::new((0, true));
+/// dependency problems that were already shown, so reloading doesn't show them again
+static WARNED_DEPENDENCIES: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+/// folders of the mods that ship a file another mod ships too, keyed by folder, None until scanned
+static CONFLICTS: Mutex<Option<HashMap<String, HashSet<String>>>> = Mutex::new(None);
+static SCANNING_CONFLICTS: AtomicBool = AtomicBool::new(false);
+/// what the installed mods are filtered by, while searching they're ranked by how well they match
+static MOD_SEARCH: Mutex<String> = Mutex::new(String::new());
+static GROUP_BY_AUTHOR: AtomicBool = AtomicBool::new(false);
+/// lowercased authors whose group is collapsed in the installed mods list
+static COLLAPSED_AUTHORS: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Where an installed mod was downloaded from, keyed by the mod's folder name in the config
+#[derive(Clone, Deserialize, Serialize, Default)]
+pub struct ModProvenance {
+    pub gb_id: i32,
+    #[serde(default)]
+    pub file_id: i32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub file: String,
+}
+
+/// Summary of an installed mod that gets copied to the clipboard for troubleshooting
+#[derive(Serialize)]
+pub struct ModDebugInfo {
+    pub name: String,
+    pub author: String,
+    pub version: String,
+    pub enabled: bool,
+    pub dirname: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gb_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependency_issues: Vec<String>,
+}
+
+impl From<DivaMod> for ModDebugInfo {
+    fn from(value: DivaMod) -> Self {
+        let provenance = get_provenance(&value);
+        let dependencies = value.dependencies.clone();
+        let dependency_issues = MODS
+            .try_lock()
+            .map(|mods| dependency_issues(&value, &mods))
+            .unwrap_or_default();
+        let element = DivaModElement::from(value);
+        Self {
+            name: element.name.to_string(),
+            author: element.author.to_string(),
+            version: element.version.to_string(),
+            enabled: element.enabled,
+            dirname: element.dirname.to_string(),
+            path: element.path.to_string(),
+            gb_id: provenance.as_ref().map(|p| p.gb_id),
+            file_id: provenance.as_ref().map(|p| p.file_id),
+            file: provenance.map(|p| p.file),
+            dependencies,
+            dependency_issues,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct DivaMod {
     pub path: String,
     pub config: DocumentMut,
+    /// names or folders of the mods this one needs, read from `dependencies` in its config.toml
+    pub dependencies: Vec<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -102,14 +192,31 @@ impl From<DivaMod> for DivaModElement {
             None => "".to_string(),
         };
 
+        let enabled = value.config["enabled"].as_bool().unwrap_or(true);
+        // only enabled mods are flagged, a disabled mod can't fail because of its dependencies
+        let issues = match MODS.try_lock() {
+            Ok(mods) if enabled => dependency_issues(&value, &mods),
+            _ => vec![],
+        };
+
         DivaModElement {
             name: value.config["name"].as_str().unwrap().into(),
             author: author.into(),
             description: description.into(),
             version: version.into(),
-            enabled: value.config["enabled"].as_bool().unwrap_or(true).clone(),
+            enabled,
             path: value.path.clone().into(),
+            pinned: value.dir_name().is_some_and(|d| is_pinned(&d)),
+            size: cached_mod_size(&value).map_or(-1, size_kib),
             dirname: value.dir_name().unwrap_or("".to_string()).into(),
+            dependencies: value.dependencies.join(", ").into(),
+            dependency_issues: issues.join(", ").into(),
+            last_used: last_used_text(&value).into(),
+            state: if enabled {
+                ModState::Enabled
+            } else {
+                ModState::Disabled
+            },
         }
     }
 }
@@ -126,20 +233,25 @@ impl From<DivaMod> for ModPackMod {
 
 impl DivaMod {
     pub fn search(self: &Self, term: &String) -> bool {
-        let right = match self.dir_name() {
-            Some(name) => name,
-            None => self.config["name"]
-                .as_str()
-                .unwrap_or("")
-                .to_string()
-                .clone(),
-        };
-        let name = self.config["name"]
-            .as_str()
-            .unwrap_or("")
-            .to_string()
-            .to_lowercase();
-        right.to_lowercase().contains(term) || name.contains(term)
+        self.search_score(term).is_some()
+    }
+
+    /// Fuzzy matches the term against the mod's name, folder and author, authors rank a bit lower
+    pub fn search_score(self: &Self, term: &str) -> Option<i32> {
+        let name = self.config["name"].as_str().unwrap_or("");
+        let author = self
+            .config
+            .get("author")
+            .and_then(|a| a.as_str())
+            .unwrap_or("");
+        [
+            fuzzy_score(term, name),
+            self.dir_name().and_then(|dir| fuzzy_score(term, &dir)),
+            fuzzy_score(term, author).map(|s| s - 5),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
     }
 
     #[deprecated]
@@ -211,13 +323,10 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
 
     let weak = ui.as_weak();
     ui.global::<ModLogic>().on_set_search(move |term| {
-        let term = term.to_string().to_lowercase();
-        let mods = get_mods()
-            .iter()
-            .cloned()
-            .filter(|m| m.search(&term))
-            .collect();
-        let _ = set_mods_table(&mods, weak.clone());
+        if let Ok(mut search) = MOD_SEARCH.lock() {
+            *search = term.trim().to_string();
+        }
+        let _ = set_mods_table(&get_mods(), weak.clone());
     });
 
     ui.global::<ModLogic>().on_load_mods(move || {
@@ -313,11 +422,14 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
                 Ok(mods) => mods,
                 Err(_) => return,
             };
-            let m = match gmods.get_mut(&module.dir_name().unwrap()) {
+            let m = match gmods.get_mut(&module.dir_name().unwrap_or_default()) {
                 Some(m) => m,
                 None => return,
             };
-            m.config["enabled"] = value(!m.config["enabled"].as_bool().unwrap());
+            m.config["enabled"] = value(!m.config["enabled"].as_bool().unwrap_or(true));
+            if let Some(dir) = m.dir_name() {
+                record_action(ModAction::Toggle(dir));
+            }
             let buf = PathBuf::from(m.path.clone());
             #[cfg(debug_assertions)]
             println!("{}", buf.display());
@@ -340,38 +452,38 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
                 Err(_) => return,
             };
             applied = cfg.applied_pack.clone();
+            let enabled = m.config["enabled"].as_bool().unwrap_or(true);
+            if enabled {
+                if let Some(name) = m.config["name"].as_str() {
+                    mark_mods_used(&mut cfg, [name.to_owned()]);
+                }
+            }
             if cfg.applied_pack != "All Mods" && cfg.applied_pack != "" {
                 let mut packs = match MOD_PACKS.try_lock() {
                     Ok(packs) => packs,
                     Err(_) => return,
                 };
-                let pack = match packs.get_mut(&cfg.applied_pack) {
+                let pack = match loaded_pack(&mut packs, &cfg.applied_pack) {
                     Some(p) => p,
                     None => return,
                 };
-                let idx = pack
-                    .mods
-                    .iter()
-                    .position(|m| m.name == module.name.to_string());
-                if let Some(idx) = idx {
-                    pack.mods[idx].enabled = m.config["enabled"].as_bool().unwrap();
-                }
+                set_mod_enabled(&mut pack.mods, &m, enabled);
                 let pack = pack.clone();
-                match save_modpack_sync(pack) {
+                match save_modpack_sync(pack, ExistingPack::Overwrite) {
                     Ok(_) => {}
                     Err(e) => {
                         eprintln!("{e}");
                         return;
                     }
                 }
-            } else {
-                let idx = cfg
-                    .priority
-                    .iter()
-                    .position(|m| m.name == module.name.to_string());
-                if let Some(idx) = idx {
-                    cfg.priority[idx].enabled = m.config["enabled"].as_bool().unwrap();
+                // the global priority isn't touched here so the config only needs saving for the timestamp
+                if enabled {
+                    if let Err(e) = write_config_sync(cfg.clone()) {
+                        eprintln!("{e}");
+                    }
                 }
+            } else {
+                set_mod_enabled(&mut cfg.priority, &m, enabled);
                 match write_config_sync(cfg.clone()) {
                     Ok(_) => {}
                     Err(e) => {
@@ -397,7 +509,20 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
         tokio::spawn(async move {
             let res = picker.pick_file().await;
             if let Some(file_handle) = res {
-                match unpack_mod_path(file_handle.path().to_path_buf()).await {
+                let path = file_handle.path().to_path_buf();
+                if !archive_looks_like_mod(&path)
+                    && !ask_confirmation(
+                        "No Mod Found".to_owned(),
+                        format!(
+                            "{} doesn't contain a config.toml, it might not be a DivaModLoader mod.\n\nInstall it anyway?",
+                            file_handle.file_name()
+                        ),
+                    )
+                    .await
+                {
+                    return;
+                }
+                match unpack_mod_path(path, ExistingMod::Ask).await {
                     Ok(_) => {
                         // waiting for this because idk, sometimes something goes wrong and the table fails to load properly will need to debug later
                         tokio::time::sleep(Duration::from_millis(5)).await;
@@ -417,25 +542,25 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
     ui.global::<ModLogic>().on_set_priority(move |old, new| {
         if let Ok(mut cfg) = R4D_CFG.lock() {
             if cfg.applied_pack == "" || cfg.applied_pack == "All Mods" {
+                snapshot_order(&cfg.applied_pack, &cfg.priority);
                 let old = min(old as usize, cfg.priority.len() - 1);
                 let item = cfg.priority.remove(old);
                 let new = max(0, min(new as usize, cfg.priority.len()));
                 cfg.priority.insert(new, item);
+                record_action(ModAction::Reorder {
+                    pack: cfg.applied_pack.clone(),
+                    old: old as i32,
+                    new: new as i32,
+                });
                 let lcfg = cfg.clone();
                 let ui_priority_handle = ui_priority_handle.clone();
                 tokio::spawn(async move {
                     match write_config(lcfg).await {
                         Ok(_) => {
+                            refresh_order_changes(ui_priority_handle.clone());
                             let mods = get_mods_in_order();
                             let _ = set_mods_table(&mods, ui_priority_handle.clone());
-                            let _ = ui_priority_handle.upgrade_in_event_loop(move |ui| {
-                                let mods_model: VecModel<DivaModElement> = VecModel::default();
-                                for diva_mod in mods.clone() {
-                                    mods_model.push(diva_mod.into());
-                                }
-                                let model = ModelRc::new(mods_model);
-                                ui.set_pack_mods(model);
-                            });
+                            refresh_pack_mods(ui_priority_handle.clone());
                         }
                         Err(e) => {
                             let msg =
@@ -446,15 +571,21 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
                 });
             } else if let Ok(mut packs) = MOD_PACKS.try_lock() {
                 let applied = cfg.applied_pack.clone();
-                if let Some(pack) = packs.get_mut(&cfg.applied_pack) {
+                if let Some(pack) = loaded_pack(&mut packs, &cfg.applied_pack) {
+                    snapshot_order(&applied, &pack.mods);
                     let old = min(old as usize, pack.mods.len() - 1);
                     let item = pack.mods.remove(old as usize);
                     let new = max(0, min(new as usize, pack.mods.len()));
                     pack.mods.insert(new, item);
+                    record_action(ModAction::Reorder {
+                        pack: applied.clone(),
+                        old: old as i32,
+                        new: new as i32,
+                    });
                     let pack = pack.clone();
                     let ui_priority_handle = ui_priority_handle.clone();
                     tokio::spawn(async move {
-                        if save_modpack(pack).await.is_ok() {
+                        if save_modpack(pack, ExistingPack::Overwrite).await.is_ok() {
                             let _ = ui_priority_handle.upgrade_in_event_loop(move |ui| {
                                 ui.global::<ModpackLogic>()
                                     .invoke_change_modpack(applied.into());
@@ -521,6 +652,118 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
             editdialog.show().unwrap();
         });
 
+    let weak = ui.as_weak();
+    ui.global::<WindowLogic>().on_open_export_dialog(move || {
+        let dialog = ExportListDialog::new().unwrap();
+        dialog.invoke_set_color_scheme(weak.unwrap().get_color_scheme());
+        let dweak = dialog.as_weak();
+        dialog.on_close(move || {
+            dweak.unwrap().hide().unwrap();
+        });
+        let dweak = dialog.as_weak();
+        dialog.on_export(move |opts| {
+            dweak.unwrap().hide().unwrap();
+            let contents = format_mod_list(&get_mods_in_order(), &opts);
+            let (filter, ext) = match opts.markdown {
+                true => ("Markdown", "md"),
+                false => ("CSV", "csv"),
+            };
+            let picker = AsyncFileDialog::new()
+                .add_filter(filter, &[ext])
+                .set_file_name(format!("mods.{ext}"))
+                .set_directory(dirs::home_dir().unwrap());
+            tokio::spawn(async move {
+                if let Some(file) = picker.save_file().await {
+                    if let Err(e) = tokio::fs::write(file.path(), contents).await {
+                        open_error_window(e.to_string());
+                    }
+                }
+            });
+        });
+        dialog.show().unwrap();
+    });
+
+    let weak = ui.as_weak();
+    ui.global::<ModLogic>()
+        .on_sort_mods(move |column, ascending| {
+            set_mods_sort(column, ascending);
+            if let Ok(mut cfg) = R4D_CFG.try_lock() {
+                cfg.mods_sort_column = column;
+                cfg.mods_sort_ascending = ascending;
+                let cfg = cfg.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = write_config(cfg).await {
+                        eprintln!("{e}");
+                    }
+                });
+            }
+            let _ = weak.upgrade_in_event_loop(resort_mods_table);
+        });
+
+    let weak = ui.as_weak();
+    ui.global::<ModLogic>()
+        .on_set_group_by_author(move |grouped| {
+            GROUP_BY_AUTHOR.store(grouped, Ordering::Relaxed);
+            set_mod_rows(&weak.unwrap());
+        });
+
+    let weak = ui.as_weak();
+    ui.global::<ModLogic>()
+        .on_toggle_author_group(move |author| {
+            let author = author.to_lowercase();
+            if let Ok(mut collapsed) = COLLAPSED_AUTHORS.lock() {
+                if !collapsed.remove(&author) {
+                    collapsed.insert(author);
+                }
+            }
+            set_mod_rows(&weak.unwrap());
+        });
+
+    let weak = ui.as_weak();
+    ui.global::<ModLogic>().on_toggle_pin(move |module| {
+        let Some(dir) = module.dir_name() else {
+            return;
+        };
+        let cfg = match R4D_CFG.try_lock() {
+            Ok(mut cfg) => {
+                match cfg.pinned_mods.iter().position(|p| *p == dir) {
+                    Some(idx) => {
+                        cfg.pinned_mods.remove(idx);
+                    }
+                    None => cfg.pinned_mods.push(dir),
+                }
+                cfg.clone()
+            }
+            Err(_) => return,
+        };
+        if let Err(e) = write_config_sync(cfg.clone()) {
+            open_error_window(e.to_string());
+        }
+        let _ = set_mods_table(&get_mods(), weak.clone());
+        weak.unwrap()
+            .global::<ModpackLogic>()
+            .invoke_change_modpack(cfg.applied_pack.into());
+    });
+
+    let weak = ui.as_weak();
+    ui.global::<ModLogic>().on_copy_mod_info(move |module| {
+        let dir = match module.dir_name() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let diva_mod = match MODS.try_lock() {
+            Ok(mods) => mods.get(&dir).cloned(),
+            Err(_) => None,
+        };
+        let Some(diva_mod) = diva_mod else {
+            return;
+        };
+        match sonic_rs::to_string_pretty(&ModDebugInfo::from(diva_mod)) {
+            Ok(info) => weak.unwrap().invoke_copy_to_clipboard(info.into()),
+            Err(e) => open_error_window(e.to_string()),
+        }
+    });
+
     // ui.global::<ModLogic>().i
     let weak = ui.as_weak();
     ui.global::<ModLogic>().on_delete_mod(move |module| {
@@ -528,7 +771,29 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
         let ui = weak.upgrade().unwrap();
         let confirm = ConfirmDelete::new().unwrap();
         confirm.invoke_set_color_scheme(ui.get_color_scheme());
-        confirm.set_item(module.name);
+        confirm.set_item(module.name.clone());
+        let dir = module.dir_name();
+        let diva_mod = dir
+            .as_ref()
+            .and_then(|dir| MODS.try_lock().ok()?.get(dir).cloned());
+        if let Some(diva_mod) = diva_mod {
+            let size =
+                cached_mod_size(&diva_mod).unwrap_or_else(|| dir_size(&mod_folder(&diva_mod)));
+            confirm.set_size(size_kib(size));
+            let packmod = ModPackMod::from(diva_mod);
+            let packs: Vec<String> = match MOD_PACKS.try_lock() {
+                Ok(mut packs) => {
+                    load_all_packs(&mut packs);
+                    packs
+                        .values()
+                        .filter(|p| p.mods.contains(&packmod))
+                        .map(|p| p.name.clone())
+                        .collect()
+                }
+                Err(_) => vec![],
+            };
+            confirm.set_packs(packs.join(", ").into());
+        }
         let cweak = confirm.as_weak();
         confirm.on_close(move || {
             cweak.unwrap().hide().unwrap();
@@ -543,12 +808,18 @@ pub async fn init(ui: &App, dark_rx: tokio::sync::broadcast::Receiver<ColorSchem
                 if buf.eq(&PathBuf::from("/").canonicalize().unwrap()) {
                     return;
                 }
-                match fs::remove_dir_all(buf) {
-                    Ok(_) => {
+                if let Some(dir) = buf.file_name() {
+                    invalidate_mod_size(&dir.to_string_lossy());
+                }
+                match trash_mod_folder(&buf) {
+                    Ok(trash) => {
+                        record_action(ModAction::Delete { dir: buf, trash });
                         if let Ok(_) = load_mods() {
                             if let Err(e) = set_mods_table(&get_mods(), ui_weak.clone()) {
                                 open_error_window(e.to_string());
                             }
+                            // packs keep the mod, it shows up as missing there so it can be reinstalled
+                            refresh_pack_mods(ui_weak.clone());
                         }
                     }
                     Err(e) => open_error_window(e.to_string()),
@@ -581,9 +852,21 @@ pub fn load_mods_from_dir(dir: String) -> Vec<DivaMod> {
         return mods;
     }
 
-    let paths = fs::read_dir(mods_folder).unwrap();
+    let paths = match fs::read_dir(&mods_folder) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("Unable to read mods folder {mods_folder}: {e}");
+            return mods;
+        }
+    };
     for path in paths {
-        let mut mod_path = path.unwrap().path().clone();
+        let mut mod_path = match path {
+            Ok(path) => path.path(),
+            Err(e) => {
+                eprintln!("Skipping an unreadable entry of the mods folder: {e}");
+                continue;
+            }
+        };
         if mod_path.is_file() || !mod_path.clone().is_dir() {
             println!(
                 "Not a mod folder: {}",
@@ -604,6 +887,7 @@ pub fn load_mods_from_dir(dir: String) -> Vec<DivaMod> {
                 };
                 mods.push(DivaMod {
                     path: mod_p_str,
+                    dependencies: parse_dependencies(&config),
                     config,
                 });
             }
@@ -619,6 +903,58 @@ pub fn load_mods_from_dir(dir: String) -> Vec<DivaMod> {
     mods
 }
 
+/// Reads the `dependencies` of a mod's config.toml, either a list of mod names/folders or an array
+/// of tables with a `name`
+fn parse_dependencies(config: &DocumentMut) -> Vec<String> {
+    let Some(item) = config.get("dependencies") else {
+        return vec![];
+    };
+    if let Some(deps) = item.as_array() {
+        return deps
+            .iter()
+            .filter_map(|dep| match dep.as_inline_table() {
+                Some(table) => table.get("name").and_then(|n| n.as_str()),
+                None => dep.as_str(),
+            })
+            .map(|dep| dep.to_string())
+            .collect();
+    }
+    match item.as_array_of_tables() {
+        Some(tables) => tables
+            .iter()
+            .filter_map(|table| table.get("name").and_then(|n| n.as_str()))
+            .map(|dep| dep.to_string())
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// Finds an installed mod by folder or name, ignoring case
+fn find_dependency<'a>(dep: &str, mods: &'a HashMap<String, DivaMod>) -> Option<&'a DivaMod> {
+    let dep = dep.trim().to_lowercase();
+    mods.iter()
+        .find(|(dir, m)| {
+            dir.to_lowercase() == dep
+                || m.config["name"].as_str().unwrap_or("").to_lowercase() == dep
+        })
+        .map(|(_, m)| m)
+}
+
+/// Dependencies of the mod that aren't installed or are disabled
+pub fn dependency_issues(module: &DivaMod, mods: &HashMap<String, DivaMod>) -> Vec<String> {
+    module
+        .dependencies
+        .iter()
+        .filter_map(|dep| match find_dependency(dep, mods) {
+            None => Some(format!("{dep} (missing)")),
+            Some(m) if !m.config["enabled"].as_bool().unwrap_or(true) => {
+                Some(format!("{dep} (disabled)"))
+            }
+            Some(_) => None,
+        })
+        .collect()
+}
+
 pub fn save_mod_config(config_path: PathBuf, config: &DocumentMut) -> std::io::Result<()> {
     return match fs::write(config_path, config.to_string()) {
         Ok(..) => {
@@ -632,11 +968,183 @@ pub fn save_mod_config(config_path: PathBuf, config: &DocumentMut) -> std::io::R
     };
 }
 
-pub async fn unpack_mod_path(archive: PathBuf) -> compress_tools::Result<()> {
-    let mut buf = PathBuf::from(find_diva_folder().unwrap_or("./mods".to_string()));
-    // DIVA_CFG.lock().unwrap().
-    buf.push(DML_CFG.lock().unwrap().mods.clone());
-    // buf.push(diva.clone().dml.unwrap().mods);
+/// Maximum number of archives that will be unpacked out of a single download
+const MAX_NESTED_ARCHIVES: usize = 32;
+/// Refuse to extract archives with more entries than this, protects against archive bombs
+const MAX_ARCHIVE_ENTRIES: usize = 100_000;
+/// Total size the nested archives of a single download are allowed to take up, 4GiB
+const MAX_NESTED_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+const ARCHIVE_EXTENSIONS: [&str; 3] = ["zip", "rar", "7z"];
+
+pub fn is_archive_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .any(|ext| name.ends_with(&format!(".{ext}")))
+}
+
+/// Whether an archive has a config.toml or nested archives in it, unreadable archives are left for
+/// the extraction to report
+fn archive_looks_like_mod(archive: &Path) -> bool {
+    let entries = File::open(archive)
+        .ok()
+        .and_then(|f| list_archive_files(f).ok());
+    let Some(entries) = entries else {
+        return true;
+    };
+    entries.iter().any(|e| {
+        let file = e.replace('\\', "/");
+        let file = file.rsplit('/').next().unwrap_or_default();
+        file.eq_ignore_ascii_case("config.toml") || is_archive_name(file)
+    })
+}
+
+/// What to do when an archive contains a mod folder that is already installed
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExistingMod {
+    Ask,
+    Overwrite,
+}
+
+pub async fn unpack_mod_path(
+    archive: PathBuf,
+    existing: ExistingMod,
+) -> compress_tools::Result<()> {
+    let name = archive
+        .file_name()
+        .unwrap_or(OsStr::new("missing.zip"))
+        .to_string_lossy()
+        .to_string();
+    if let Some(nested) = unpack_nested_archives(&archive)? {
+        let mut install = true;
+        if nested.len() > 1 {
+            let names: Vec<String> = nested
+                .iter()
+                .filter_map(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .collect();
+            install = ask_confirmation(
+                "Multiple Mods Found".to_owned(),
+                format!(
+                    "{} contains {} mods:\n{}\n\nInstall each of them as a separate mod?",
+                    name,
+                    nested.len(),
+                    names.join("\n")
+                ),
+            )
+            .await;
+        }
+        let mut result = None;
+        if install {
+            let mut installed = Ok(());
+            for inner in nested {
+                println!("Installing nested archive: {}", inner.display());
+                if let Err(e) = extract_mod_archive(inner, existing).await {
+                    installed = Err(e);
+                    break;
+                }
+            }
+            result = Some(installed);
+        }
+        // the extracted archives are only needed for the install above, declining falls back to
+        // installing the outer archive as it is
+        if let Some(dir) = nested_extract_dir(&archive) {
+            let _ = fs::remove_dir_all(dir);
+        }
+        if let Some(result) = result {
+            return result;
+        }
+    }
+    extract_mod_archive(archive, existing).await
+}
+
+fn nested_extract_dir(archive: &PathBuf) -> Option<PathBuf> {
+    let mut buf = PathBuf::from(get_temp_folder()?);
+    buf.push(format!(
+        "nested-{}",
+        archive.file_stem().unwrap_or_default().to_string_lossy()
+    ));
+    Some(buf)
+}
+
+/// If every file in the archive is itself an archive, extract them to the temp folder and return
+/// their paths. Only goes one level deep, archives inside of the nested archives are left as is.
+pub fn unpack_nested_archives(archive: &PathBuf) -> compress_tools::Result<Option<Vec<PathBuf>>> {
+    let entries = list_archive_files(File::open(archive)?)?;
+    if entries.len() > MAX_ARCHIVE_ENTRIES {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Archive contains too many entries: {}", entries.len()),
+        )
+        .into());
+    }
+    let files: Vec<&String> = entries.iter().filter(|f| !f.ends_with("/")).collect();
+    if files.is_empty() || !files.iter().all(|f| is_archive_name(f)) {
+        return Ok(None);
+    }
+    if files.len() > MAX_NESTED_ARCHIVES {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Archive contains too many nested archives: {}", files.len()),
+        )
+        .into());
+    }
+    if archive_listed_size(archive)? > MAX_NESTED_SIZE {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Nested archives are too large to extract",
+        )
+        .into());
+    }
+    let dest = match nested_extract_dir(archive) {
+        Some(dest) => dest,
+        None => return Ok(None),
+    };
+    if dest.exists() {
+        fs::remove_dir_all(dest.clone())?;
+    }
+    fs::create_dir_all(dest.clone())?;
+    let extracted = File::open(archive)
+        .map_err(compress_tools::Error::from)
+        .and_then(|f| uncompress_archive(f, dest.as_path(), Ownership::Ignore));
+    if let Err(e) = extracted {
+        let _ = fs::remove_dir_all(dest);
+        return Err(e);
+    }
+
+    let mut nested: Vec<PathBuf> = walk_files(&dest)
+        .files
+        .into_iter()
+        .filter(|file| is_archive_name(&file.relative))
+        .map(|file| file.path)
+        .collect();
+    nested.sort();
+    Ok(Some(nested))
+}
+
+/// Adds up the sizes the archive's headers list for its entries, nothing is decompressed
+fn archive_listed_size(archive: &Path) -> compress_tools::Result<u64> {
+    let total = Rc::new(Cell::new(0u64));
+    let counter = total.clone();
+    let entries = ArchiveIteratorBuilder::new(File::open(archive)?)
+        .filter(move |_, stat| {
+            counter.set(counter.get() + stat.st_size.max(0) as u64);
+            false
+        })
+        .build()?;
+    for content in entries {
+        if let ArchiveContents::Err(e) = content {
+            return Err(e);
+        }
+    }
+    Ok(total.get())
+}
+
+async fn extract_mod_archive(
+    archive: PathBuf,
+    on_existing: ExistingMod,
+) -> compress_tools::Result<()> {
+    let buf = current_mods_dir().unwrap_or(PathBuf::from("./mods"));
     let name = archive
         .file_name()
         .unwrap_or(OsStr::new("missing.zip"))
@@ -644,31 +1152,318 @@ pub async fn unpack_mod_path(archive: PathBuf) -> compress_tools::Result<()> {
         .unwrap()
         .to_string();
     // let name = buf.extension().unwrap_or(OsStr::new("zip")).to_str().unwrap().to_string();
-    let valid = check_archive_valid_structure(File::open(archive.clone()).unwrap(), name);
-    println!("Good structure? {}", valid);
-    if !valid {
-        buf.push(archive.file_name().unwrap());
-        if !buf.exists() {
-            let _ = fs::create_dir(buf.clone());
-        }
-    }
-    let mut mod_archive = File::open(archive.clone()).unwrap();
-    let res = uncompress_archive(&mut mod_archive, buf.as_path(), Ownership::Preserve);
-    // compress tools always gives an error when extracting rar files
-    if res.is_err() && archive.extension().unwrap_or_default() == "rar" {
-        if let Err(e) = res {
+    let flatten = R4D_CFG.try_lock().map_or(true, |cfg| cfg.flatten_archives);
+    let entries = list_archive_files(File::open(archive.clone())?)?;
+    let layout = if flatten {
+        archive_layout(&entries)
+    } else if check_archive_valid_structure(File::open(archive.clone()).unwrap(), name.clone()) {
+        ArchiveLayout::ModFolders
+    } else {
+        ArchiveLayout::LooseFiles
+    };
+    println!("Archive layout: {:?}", layout);
+    let loose_dir = loose_folder_name(&archive, &buf, flatten);
+    // pinned mods have to be unpinned before an archive is allowed to overwrite them
+    let mut targets: Vec<String> = match &layout {
+        ArchiveLayout::ModFolders => entries
+            .iter()
+            .filter_map(|f| f.split('/').next().map(|d| d.to_string()))
+            .collect(),
+        ArchiveLayout::LooseFiles => vec![loose_dir.clone()],
+        ArchiveLayout::Wrapped(root) => entries
+            .iter()
+            .filter_map(|f| f.strip_prefix(&format!("{root}/")))
+            .filter_map(|f| f.split_once('/').map(|(d, _)| d.to_string()))
+            .collect(),
+    };
+    targets.sort();
+    targets.dedup();
+    let pinned_mods = pinned_mods();
+    if let Some(pinned) = targets
+        .iter()
+        .find(|d| pinned_mods.contains(d) && buf.join(d).exists())
+    {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            format!("{pinned} is pinned, unpin it before installing a different version"),
+        )
+        .into());
+    }
+    let existing: Vec<String> = targets
+        .iter()
+        .filter(|d| buf.join(d).is_dir())
+        .cloned()
+        .collect();
+    // enabled state of the mods being replaced, their place in the priority list is kept by folder name
+    let mut previous_state = vec![];
+    if !existing.is_empty() {
+        let msg = format!(
+            "The following mods are already installed:\n{}\n\nOverwrite replaces the installed files, merge keeps installed files that aren't in the new version. The current files are backed up to the temp folder either way.",
+            existing.join("\n")
+        );
+        let answer = match on_existing {
+            ExistingMod::Overwrite => DialogAnswer::Confirm,
+            ExistingMod::Ask => {
+                ask_choice(
+                    "Mod Already Installed".to_owned(),
+                    msg,
+                    Some("Overwrite".to_owned()),
+                    Some("Merge".to_owned()),
+                )
+                .await
+            }
+        };
+        if answer == DialogAnswer::Cancel {
+            println!("Install of {} cancelled", archive.display());
+            return Ok(());
+        }
+        for dir in &existing {
+            let path = buf.join(dir);
+            previous_state.push((dir.clone(), read_enabled(&path)));
+            let backup = backup_mod_folder(&path)?;
+            println!("Backed up {} to {}", path.display(), backup.display());
+            if answer == DialogAnswer::Confirm {
+                fs::remove_dir_all(&path)?;
+            }
+        }
+    }
+    for target in &targets {
+        invalidate_mod_size(target);
+    }
+    // large archives take a while, so they're extracted off the async workers
+    let mods_dir = buf.clone();
+    let res = tokio::task::spawn_blocking(move || match &layout {
+        ArchiveLayout::ModFolders => uncompress_mod(&archive, &mods_dir),
+        ArchiveLayout::LooseFiles => {
+            let dest = mods_dir.join(&loose_dir);
+            if !dest.exists() {
+                let _ = fs::create_dir(&dest);
+            }
+            uncompress_mod(&archive, &dest)
+        }
+        ArchiveLayout::Wrapped(root) => unwrap_mod_folders(&archive, root, &targets, &mods_dir),
+    })
+    .await
+    .unwrap_or_else(|e| Err(io::Error::new(ErrorKind::Other, e.to_string()).into()));
+    if res.is_ok() {
+        for (dir, enabled) in previous_state {
+            if let Some(enabled) = enabled {
+                restore_enabled(&buf.join(dir), enabled);
+            }
+        }
+    }
+    return res;
+}
+
+/// Whether an install failed because the archive couldn't be read, rather than because of the mods
+/// folder
+pub fn is_extraction_error(e: &compress_tools::Error) -> bool {
+    matches!(
+        e,
+        compress_tools::Error::Extraction(_)
+            | compress_tools::Error::Encoding(_)
+            | compress_tools::Error::NullArchive
+            | compress_tools::Error::Unknown
+    )
+}
+
+/// Folder loose files are wrapped in, named after the archive. The extension is only dropped from
+/// the name when flattening and the mod wasn't already installed under the full archive name, so
+/// updating a mod installed before flattening keeps its folder instead of adding a second copy
+fn loose_folder_name(archive: &Path, mods_dir: &Path, flatten: bool) -> String {
+    let name = archive
+        .file_name()
+        .unwrap_or(OsStr::new("missing.zip"))
+        .to_string_lossy()
+        .to_string();
+    if !flatten || mods_dir.join(&name).is_dir() {
+        return name;
+    }
+    archive
+        .file_stem()
+        .unwrap_or(OsStr::new("missing"))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Extracts an archive into `dest`, compress tools always gives an error when extracting rar files
+/// so that one is ignored
+fn uncompress_mod(archive: &PathBuf, dest: &Path) -> compress_tools::Result<()> {
+    let mut mod_archive = File::open(archive)?;
+    let res = uncompress_archive(&mut mod_archive, dest, Ownership::Preserve);
+    if let Err(e) = &res {
+        if archive.extension().unwrap_or_default() == "rar" {
             eprintln!("{e}");
             if e.to_string()
                 == "Extraction error: 'Can't decompress an entry marked as a directory'"
             {
                 println!("Ignoring this error on rar archive");
                 return Ok(());
-            } else {
-                return Err(e.into());
             }
         }
     }
-    return res;
+    res
+}
+
+/// Extracts an archive to the temp folder and moves the mod folders inside its wrapping `root`
+/// folder into the mods folder, files of mods that are already installed are merged
+fn unwrap_mod_folders(
+    archive: &PathBuf,
+    root: &str,
+    targets: &Vec<String>,
+    mods_dir: &Path,
+) -> compress_tools::Result<()> {
+    let mut tmp = PathBuf::from(get_temp_folder().unwrap_or("./tmp".to_owned()));
+    tmp.push(format!(
+        "unwrap-{}",
+        archive.file_stem().unwrap_or_default().to_string_lossy()
+    ));
+    if tmp.exists() {
+        fs::remove_dir_all(&tmp)?;
+    }
+    fs::create_dir_all(&tmp)?;
+    uncompress_mod(archive, &tmp)?;
+    let res = targets.iter().try_for_each(|target| {
+        let src = tmp.join(root).join(target);
+        let dst = mods_dir.join(target);
+        match dst.exists() {
+            true => copy_dir(&src, &dst),
+            false => move_dir(&src, &dst),
+        }
+    });
+    let _ = fs::remove_dir_all(&tmp);
+    Ok(res?)
+}
+
+fn read_enabled(mod_dir: &Path) -> Option<bool> {
+    let config = fs::read_to_string(mod_dir.join("config.toml")).ok()?;
+    config.parse::<DocumentMut>().ok()?["enabled"].as_bool()
+}
+
+fn restore_enabled(mod_dir: &Path, enabled: bool) {
+    let path = mod_dir.join("config.toml");
+    let Ok(config) = fs::read_to_string(&path) else {
+        return;
+    };
+    if let Ok(mut config) = config.parse::<DocumentMut>() {
+        config["enabled"] = value(enabled);
+        if let Err(e) = save_mod_config(path, &config) {
+            eprintln!("Unable to restore enabled state: {e}");
+        }
+    }
+}
+
+/// Copies a mod folder into the temp folder's backups before it is overwritten
+fn backup_mod_folder(mod_dir: &Path) -> io::Result<PathBuf> {
+    let mut backup = PathBuf::from(get_temp_folder().unwrap_or("./tmp".to_owned()));
+    backup.push("backups");
+    backup.push(format!(
+        "{}-{}",
+        mod_dir.file_name().unwrap_or_default().to_string_lossy(),
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    copy_dir(mod_dir, &backup)?;
+    Ok(backup)
+}
+
+/// Moves a deleted mod folder into the temp folder's trash so the delete can be undone
+fn trash_mod_folder(mod_dir: &Path) -> io::Result<PathBuf> {
+    let mut trash = PathBuf::from(get_temp_folder().unwrap_or("./tmp".to_owned()));
+    trash.push("trash");
+    trash.push(format!(
+        "{}-{}",
+        mod_dir.file_name().unwrap_or_default().to_string_lossy(),
+        chrono::Local::now().format("%Y%m%d-%H%M%S%.3f")
+    ));
+    move_dir(mod_dir, &trash)?;
+    Ok(trash)
+}
+
+/// Renames a folder, falling back to copying it when the destination is on another drive
+pub fn move_dir(src: &Path, dst: &Path) -> io::Result<()> {
+    if dst.exists() {
+        return Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", dst.display()),
+        ));
+    }
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    copy_dir(src, dst)?;
+    fs::remove_dir_all(src)
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// How the files in a mod archive are laid out, see [`archive_layout`]
+#[derive(Debug, PartialEq)]
+pub enum ArchiveLayout {
+    /// every folder at the root is a mod and is extracted into the mods folder as is
+    ModFolders,
+    /// the mod's files are at the root and get wrapped in a folder named after the archive
+    LooseFiles,
+    /// a single folder at the root holds the mod folders, its contents are installed instead of it
+    Wrapped(String),
+}
+
+/// Works out the layout of an archive from its entry names. A mod is a folder with a config.toml
+/// in it, so
+/// - a config.toml at the root means the files are loose
+/// - config.toml files one folder deep mean the root folders are mods
+/// - config.toml files two folders deep under a single root folder mean that folder wraps the mods
+///
+/// Archives without a config.toml at one of those places are treated as loose files if anything
+/// but folders is at the root and as mod folders otherwise. `__MACOSX` folders are ignored and rar
+/// archives don't mark folders with a trailing `/`, so an entry counts as a folder if other
+/// entries are inside of it.
+pub fn archive_layout(entries: &[String]) -> ArchiveLayout {
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|e| e.replace('\\', "/"))
+        .filter(|e| !e.starts_with("__MACOSX/") && e != "__MACOSX")
+        .collect();
+    let is_dir = |root: &str| entries.iter().any(|e| e.starts_with(&format!("{root}/")));
+    let configs: Vec<usize> = entries
+        .iter()
+        .filter(|e| e.rsplit('/').next() == Some("config.toml"))
+        .map(|e| e.matches('/').count())
+        .collect();
+    let mut roots: Vec<&str> = entries
+        .iter()
+        .filter_map(|e| e.split('/').next())
+        .filter(|r| !r.is_empty())
+        .collect();
+    roots.sort();
+    roots.dedup();
+    if configs.contains(&0) {
+        return ArchiveLayout::LooseFiles;
+    }
+    if !configs.is_empty() && configs.iter().all(|depth| *depth == 1) {
+        return ArchiveLayout::ModFolders;
+    }
+    if roots.len() == 1 && !configs.is_empty() && configs.iter().all(|depth| *depth == 2) {
+        return ArchiveLayout::Wrapped(roots[0].to_owned());
+    }
+    match roots.iter().all(|r| is_dir(r)) {
+        true => ArchiveLayout::ModFolders,
+        false => ArchiveLayout::LooseFiles,
+    }
 }
 
 pub fn check_archive_valid_structure(archive: File, name: String) -> bool {
@@ -721,34 +1516,392 @@ pub fn load_diva_ml_config(diva_folder: &str) -> Option<DivaModLoader> {
     return loader;
 }
 
+/// Works out why the mods table would be empty, a search that matches nothing is not an empty state
+pub fn mods_empty_state() -> ModsEmptyState {
+    let diva_dir = match DIVA_DIR.try_lock() {
+        Ok(dir) => dir.clone(),
+        Err(_) => return ModsEmptyState::None,
+    };
+    if diva_dir.is_empty() || !PathBuf::from(&diva_dir).join("DivaMegaMix.exe").exists() {
+        return ModsEmptyState::NoDiva;
+    }
+    match MODS.try_lock() {
+        Ok(mods) if mods.is_empty() => ModsEmptyState::NoMods,
+        _ => ModsEmptyState::None,
+    }
+}
+
 pub fn set_mods_table(mods: &Vec<DivaMod>, ui_handle: Weak<App>) -> Result<(), EventLoopError> {
-    let mods = mods.clone();
-    ui_handle.upgrade_in_event_loop(move |ui| {
-        let mods_model: VecModel<DivaModElement> = VecModel::default();
+    let term = MOD_SEARCH.lock().map(|t| t.clone()).unwrap_or_default();
+    let searching = !term.is_empty();
+    let mods = if searching {
+        rank_mods(mods, &term)
+    } else {
+        mods.clone()
+    };
+    let empty_state = mods_empty_state();
+    let unsized_mods: Vec<DivaMod> = mods
+        .iter()
+        .filter(|m| cached_mod_size(m).is_none())
+        .cloned()
+        .collect();
+    let size_handle = ui_handle.clone();
+    let res = ui_handle.upgrade_in_event_loop(move |ui| {
+        ui.set_mods_empty_state(empty_state);
         let mut mods = mods.clone();
-        mods.sort_by_key(|m| {
-            m.config["name"]
-                .as_str()
-                .unwrap()
-                .to_string()
-                .to_lowercase()
-        });
-        for diva_mod in mods {
-            mods_model.push(diva_mod.into());
+        if !searching {
+            mods.sort_by_key(|m| {
+                m.config["name"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+                    .to_lowercase()
+            });
         }
-        let model = ModelRc::new(mods_model);
+        let mut elements: Vec<DivaModElement> = mods.into_iter().map(|m| m.into()).collect();
+        // search results stay in the order of how well they match
+        if !searching {
+            sort_mod_elements(&mut elements);
+        }
+        let model = ModelRc::new(VecModel::from(elements));
         ui.set_mods(model);
-    })
+        set_mod_rows(&ui);
+    });
+    if !unsized_mods.is_empty() {
+        fill_mod_sizes(unsized_mods, size_handle);
+    }
+    res
+}
+
+/// Filters the mods by the search term and sorts them best match first, mods in a pack whose name
+/// matches are included below the mods that match by themselves
+fn rank_mods(mods: &Vec<DivaMod>, term: &str) -> Vec<DivaMod> {
+    // only the packs whose name matches need their mods read
+    let packs: Vec<(i32, ModPack)> = match MOD_PACKS.lock() {
+        Ok(mut packs) => {
+            let matched: Vec<(i32, String)> = packs
+                .values()
+                .filter_map(|p| fuzzy_score(term, &p.name).map(|s| (s - 10, p.name.clone())))
+                .collect();
+            matched
+                .into_iter()
+                .filter_map(|(s, name)| Some((s, loaded_pack(&mut packs, &name)?.clone())))
+                .collect()
+        }
+        Err(_) => vec![],
+    };
+    let mut ranked: Vec<(i32, &DivaMod)> = mods
+        .iter()
+        .filter_map(|m| {
+            let pack_score = packs
+                .iter()
+                .filter(|(_, pack)| pack.mods.iter().any(|p| p == m))
+                .map(|(score, _)| *score)
+                .max();
+            m.search_score(term).max(pack_score).map(|score| (score, m))
+        })
+        .collect();
+    ranked.sort_by_key(|(score, m)| {
+        (
+            -score,
+            m.config["name"].as_str().unwrap_or("").to_lowercase(),
+        )
+    });
+    ranked.into_iter().map(|(_, m)| m.clone()).collect()
+}
+
+/// Sets how the installed mods table is sorted, this only changes how the list is shown and never
+/// the load order
+pub fn set_mods_sort(column: i32, ascending: bool) {
+    if let Ok(mut sort) = MOD_SORT.lock() {
+        *sort = (column, ascending);
+    }
+}
+
+/// Sorts the installed mods table by the column picked in the table header, the first column is
+/// the name, the second is the folder size and the third is when the mod was last used. -1 keeps
+/// the global load order
+fn sort_mod_elements(elements: &mut Vec<DivaModElement>) {
+    let (column, ascending) = match MOD_SORT.lock() {
+        Ok(sort) => *sort,
+        Err(_) => return,
+    };
+    match column {
+        -1 => {
+            let priority = match R4D_CFG.try_lock() {
+                Ok(cfg) => cfg.priority.clone(),
+                Err(_) => return,
+            };
+            elements.sort_by_key(|e| {
+                priority
+                    .iter()
+                    .position(|p| p.path == e.path.as_str())
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        // unknown sizes are -1 so they end up at the start, this is fine as they are filled in shortly after
+        1 => elements.sort_by_key(|e| e.size),
+        2 => {
+            let last_used = match R4D_CFG.try_lock() {
+                Ok(cfg) => cfg.last_used.clone(),
+                Err(_) => return,
+            };
+            elements.sort_by_key(|e| last_used.get(e.name.as_str()).copied().unwrap_or(0));
+        }
+        _ => elements.sort_by_key(|e| e.name.to_lowercase()),
+    }
+    if !ascending {
+        elements.reverse();
+    }
+}
+
+fn size_kib(size: u64) -> i32 {
+    min(size / 1024, i32::MAX as u64) as i32
+}
+
+fn mod_folder(module: &DivaMod) -> PathBuf {
+    let mut buf = PathBuf::from(module.path.clone());
+    buf.pop();
+    buf
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let walk = walk_files(dir);
+    if walk.skipped > 0 {
+        println!(
+            "Size of {} leaves out {} unreadable entries",
+            dir.display(),
+            walk.skipped
+        );
+    }
+    walk.files.iter().map(|f| f.size).sum()
+}
+
+/// Returns the size of the mod's folder if it was calculated since the folder last changed
+pub fn cached_mod_size(module: &DivaMod) -> Option<u64> {
+    let dir = module.dir_name()?;
+    let modified = mod_folder(module)
+        .metadata()
+        .and_then(|m| m.modified())
+        .ok();
+    let sizes = MOD_SIZES.try_lock().ok()?;
+    match sizes.get(&dir) {
+        Some((size, time)) if *time == modified => Some(*size),
+        _ => None,
+    }
+}
+
+pub fn invalidate_mod_size(dir_name: &str) {
+    if let Ok(mut sizes) = MOD_SIZES.lock() {
+        sizes.remove(dir_name);
+    }
+}
+
+/// Walks the folders of the given mods off of the ui thread, filling in the size column as each one finishes
+fn fill_mod_sizes(mods: Vec<DivaMod>, ui_handle: Weak<App>) {
+    std::thread::spawn(move || {
+        for module in mods {
+            let Some(dir) = module.dir_name() else {
+                continue;
+            };
+            match SIZING_MODS.lock() {
+                Ok(mut sizing) if sizing.insert(dir.clone()) => {}
+                _ => continue,
+            }
+            let folder = mod_folder(&module);
+            let modified = folder.metadata().and_then(|m| m.modified()).ok();
+            let size = dir_size(&folder);
+            if let Ok(mut sizes) = MOD_SIZES.lock() {
+                sizes.insert(dir.clone(), (size, modified));
+            }
+            if let Ok(mut sizing) = SIZING_MODS.lock() {
+                sizing.remove(&dir);
+            }
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                let model = ui.get_mods();
+                for i in 0..model.row_count() {
+                    let mut row = model.row_data(i).unwrap();
+                    if row.dirname == dir {
+                        row.size = size_kib(size);
+                        model.set_row_data(i, row);
+                        break;
+                    }
+                }
+                let rows = ui.get_mod_rows();
+                for i in 0..rows.row_count() {
+                    let mut row = rows.row_data(i).unwrap();
+                    if !row.header && row.module.dirname == dir {
+                        row.module.size = size_kib(size);
+                        rows.set_row_data(i, row);
+                        return;
+                    }
+                }
+            });
+        }
+        // the sizes are all known now so sorting by them can be done properly
+        if MOD_SORT.lock().is_ok_and(|sort| sort.0 == 1) {
+            let _ = ui_handle.upgrade_in_event_loop(resort_mods_table);
+        }
+    });
+}
+
+/// Collects the paths of the files in a mod's subfolders relative to the mod folder, lowercased
+fn mod_files(dir: &Path) -> Vec<String> {
+    let walk = walk_files(dir);
+    if walk.skipped > 0 {
+        println!(
+            "Conflict scan of {} leaves out {} unreadable entries",
+            dir.display(),
+            walk.skipped
+        );
+    }
+    walk.files
+        .into_iter()
+        .map(|f| f.relative.to_lowercase())
+        // files in the mod's root are its own config, readme and previews. DML merges the mod_
+        // databases of every mod so those don't replace each other either
+        .filter(|f| {
+            f.rsplit_once('/')
+                .is_some_and(|(_, name)| !name.starts_with("mod_"))
+        })
+        .collect()
+}
+
+/// Finds mods that replace the same files as each other, for each mod folder the other mods
+/// sharing a file with it
+fn find_conflicts(mods: &Vec<DivaMod>) -> HashMap<String, HashSet<String>> {
+    let mut owners: HashMap<String, Vec<String>> = HashMap::new();
+    for module in mods {
+        let Some(dir) = module.dir_name() else {
+            continue;
+        };
+        for file in mod_files(&mod_folder(module)) {
+            owners.entry(file).or_default().push(dir.clone());
+        }
+    }
+    let mut conflicts: HashMap<String, HashSet<String>> = HashMap::new();
+    for dirs in owners.values().filter(|dirs| dirs.len() > 1) {
+        for dir in dirs {
+            let others = dirs.iter().filter(|d| *d != dir).cloned();
+            conflicts.entry(dir.clone()).or_default().extend(others);
+        }
+    }
+    conflicts
+}
+
+/// Enabled mods that share files with another enabled mod, None if the mods weren't scanned yet
+pub fn conflicting_mods(mods: &Vec<DivaMod>) -> Option<HashSet<String>> {
+    let conflicts = CONFLICTS.lock().ok()?;
+    let conflicts = conflicts.as_ref()?;
+    let enabled: HashSet<String> = mods
+        .iter()
+        .filter(|m| m.config["enabled"].as_bool().unwrap_or(true))
+        .filter_map(|m| m.dir_name())
+        .collect();
+    Some(
+        enabled
+            .iter()
+            .filter(|dir| {
+                conflicts
+                    .get(*dir)
+                    .is_some_and(|others| others.iter().any(|o| enabled.contains(o)))
+            })
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Looks for mods sharing files in the background and calls `done` once they're known
+pub fn scan_conflicts(done: impl FnOnce() + Send + 'static) {
+    if SCANNING_CONFLICTS.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let mods = get_mods();
+    std::thread::spawn(move || {
+        let conflicts = find_conflicts(&mods);
+        if let Ok(mut cache) = CONFLICTS.lock() {
+            *cache = Some(conflicts);
+        }
+        SCANNING_CONFLICTS.store(false, Ordering::Relaxed);
+        done();
+    });
+}
+
+fn resort_mods_table(ui: App) {
+    let mut elements: Vec<DivaModElement> = ui.get_mods().iter().collect();
+    sort_mod_elements(&mut elements);
+    ui.set_mods(ModelRc::new(VecModel::from(elements)));
+    set_mod_rows(&ui);
+}
+
+fn author_group(module: &DivaModElement) -> String {
+    match module.author.trim() {
+        "" => "Unknown".to_owned(),
+        author => author.to_owned(),
+    }
+}
+
+/// Builds the rows of the installed mods list, when grouping by author the mods keep their sort
+/// order within each group and collapsed groups only show their header
+fn build_mod_rows(mods: Vec<DivaModElement>) -> Vec<ModPickRow> {
+    let row = |module: DivaModElement| ModPickRow {
+        header: false,
+        author: SharedString::new(),
+        count: 0,
+        expanded: true,
+        module,
+    };
+    if !GROUP_BY_AUTHOR.load(Ordering::Relaxed) {
+        return mods.into_iter().map(row).collect();
+    }
+    let mut groups: Vec<(String, Vec<DivaModElement>)> = vec![];
+    for module in mods {
+        let author = author_group(&module);
+        match groups
+            .iter_mut()
+            .find(|(a, _)| a.to_lowercase() == author.to_lowercase())
+        {
+            Some((_, group)) => group.push(module),
+            None => groups.push((author, vec![module])),
+        }
+    }
+    groups.sort_by_key(|(author, _)| author.to_lowercase());
+    let collapsed = COLLAPSED_AUTHORS
+        .lock()
+        .map(|c| c.clone())
+        .unwrap_or_default();
+    let mut rows = vec![];
+    for (author, group) in groups {
+        let expanded = !collapsed.contains(&author.to_lowercase());
+        rows.push(ModPickRow {
+            header: true,
+            author: author.into(),
+            count: group.len() as i32,
+            expanded,
+            module: DivaModElement::default(),
+        });
+        if expanded {
+            rows.extend(group.into_iter().map(row));
+        }
+    }
+    rows
+}
+
+fn set_mod_rows(ui: &App) {
+    let rows = build_mod_rows(ui.get_mods().iter().collect());
+    ui.set_mod_rows(ModelRc::new(VecModel::from(rows)));
 }
 //std::io::Result<()>
 pub fn load_mods() -> Result<(), Box<dyn Error + Send + Sync>> {
     let dir = DIVA_DIR.try_lock().unwrap().clone();
-    let mut buf = PathBuf::from(dir);
+    let mods_dir = MODS_DIR.try_lock().map_err(|e| e.to_string())?.clone();
+    let buf = find_mods_dir(Path::new(&dir), &mods_dir);
     let mut gconf = R4D_CFG.try_lock().unwrap();
-    buf.push("mods");
     let buf = buf.canonicalize()?;
     buf.display().to_string();
-    let mods = load_mods_from_dir(buf.display().to_string());
+    let mut mods = load_mods_from_dir(buf.display().to_string());
+    // read_dir order depends on the filesystem, new mods are appended to the priority alphabetically
+    mods.sort_by_cached_key(|m| m.dir_name().unwrap_or_default().to_lowercase());
     let mut dmods = MODS.lock().unwrap();
     let mut mod_map = HashMap::new();
     for mut module in mods {
@@ -770,6 +1923,11 @@ pub fn load_mods() -> Result<(), Box<dyn Error + Send + Sync>> {
         }
     }
     *dmods = mod_map.clone();
+    warn_dependency_issues(&mod_map);
+    // the files might have changed so conflicts are looked for again when they're needed
+    if let Ok(mut conflicts) = CONFLICTS.lock() {
+        *conflicts = None;
+    }
     if mod_map.len() != gconf.priority.len() {
         let mut mods: Vec<DivaMod> = vec![];
         for p in gconf.priority.clone() {
@@ -806,6 +1964,33 @@ pub fn load_mods() -> Result<(), Box<dyn Error + Send + Sync>> {
     Ok(())
 }
 
+/// Shows which enabled mods have missing or disabled dependencies, each problem is only shown once
+/// per session
+fn warn_dependency_issues(mods: &HashMap<String, DivaMod>) {
+    let mut problems: Vec<String> = mods
+        .values()
+        .filter(|m| m.config["enabled"].as_bool().unwrap_or(true))
+        .flat_map(|m| {
+            let name = m.config["name"].as_str().unwrap_or("").to_string();
+            dependency_issues(m, mods)
+                .into_iter()
+                .map(move |issue| format!("{name} needs {issue}"))
+        })
+        .collect();
+    let Ok(mut warned) = WARNED_DEPENDENCIES.lock() else {
+        return;
+    };
+    problems.retain(|p| warned.insert(p.clone()));
+    if problems.is_empty() {
+        return;
+    }
+    problems.sort();
+    open_error_window(format!(
+        "Some enabled mods need other mods to work:\n{}",
+        problems.join("\n")
+    ));
+}
+
 pub fn get_mods() -> Vec<DivaMod> {
     let mut mods = vec![];
     if let Ok(ms) = MODS.try_lock() {
@@ -817,6 +2002,23 @@ pub fn get_mods() -> Vec<DivaMod> {
 }
 
 pub fn get_mods_in_order() -> Vec<DivaMod> {
+    get_pack_entries_in_order()
+        .into_iter()
+        .filter_map(|entry| match entry {
+            PackEntry::Installed(m) => Some(m),
+            PackEntry::Missing(_) => None,
+        })
+        .collect()
+}
+
+/// A mod in the applied pack's load order, packs keep listing mods that were uninstalled since
+pub enum PackEntry {
+    Installed(DivaMod),
+    Missing(ModPackMod),
+}
+
+/// The applied pack's load order including the mods it lists that aren't installed
+pub fn get_pack_entries_in_order() -> Vec<PackEntry> {
     let mut mods = vec![];
     let cfg = match R4D_CFG.try_lock() {
         Ok(cfg) => cfg,
@@ -831,14 +2033,14 @@ pub fn get_mods_in_order() -> Vec<DivaMod> {
     if cfg.applied_pack == "All Mods" || cfg.applied_pack == "" {
         prio = cfg.priority.clone();
     } else {
-        let packs = match MOD_PACKS.try_lock() {
+        let mut packs = match MOD_PACKS.try_lock() {
             Ok(packs) => packs,
             Err(e) => {
                 eprintln!("{e}");
                 return mods;
             }
         };
-        let pack = match packs.get(&cfg.applied_pack) {
+        let pack = match loaded_pack(&mut packs, &cfg.applied_pack) {
             Some(pack) => pack,
             None => return mods,
         };
@@ -857,11 +2059,11 @@ pub fn get_mods_in_order() -> Vec<DivaMod> {
             }
         };
         for p in prio {
-            match gmods.get(&p.dir_name().expect("dir name should unwrap")) {
+            match p.dir_name().and_then(|dir| gmods.get(&dir)) {
                 Some(m) => {
-                    mods.push(m.clone());
+                    mods.push(PackEntry::Installed(m.clone()));
                 }
-                None => {}
+                None => mods.push(PackEntry::Missing(p)),
             }
         }
     }
@@ -871,12 +2073,267 @@ pub fn get_mods_in_order() -> Vec<DivaMod> {
     mods
 }
 
+/// Remembers which GameBanana item the given mods were installed from
+pub fn record_provenance(
+    mods: &Vec<DivaMod>,
+    provenance: ModProvenance,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if mods.is_empty() {
+        return Ok(());
+    }
+    let cfg = match R4D_CFG.lock() {
+        Ok(mut cfg) => {
+            for m in mods {
+                if let Some(dir) = m.dir_name() {
+                    cfg.provenance.insert(dir, provenance.clone());
+                }
+            }
+            cfg.clone()
+        }
+        Err(e) => return Err(e.to_string().into()),
+    };
+    write_config_sync(cfg)
+}
+
+/// Stamps the given mods with the current time, the caller is expected to save the config
+pub fn mark_mods_used(cfg: &mut DivaConfig, names: impl IntoIterator<Item = String>) {
+    let now = chrono::Utc::now().timestamp();
+    for name in names {
+        cfg.last_used.insert(name, now);
+    }
+}
+
+/// When the mod was last enabled or applied in local time, empty if it never was
+fn last_used_text(module: &DivaMod) -> String {
+    let Some(name) = module.config["name"].as_str() else {
+        return String::new();
+    };
+    let time = match R4D_CFG.try_lock() {
+        Ok(cfg) => cfg.last_used.get(name).copied(),
+        Err(_) => None,
+    };
+    time.and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+/// Pinned mods are kept on their installed version and can't be overwritten by downloads. This
+/// doesn't wait for the config so it's only meant for display, anything that has to respect the pin
+/// uses [`pinned_mods`]
+pub fn is_pinned(dir_name: &str) -> bool {
+    match R4D_CFG.try_lock() {
+        Ok(cfg) => cfg.pinned_mods.iter().any(|p| p == dir_name),
+        Err(_) => false,
+    }
+}
+
+/// The pinned mod folders, waits for the config so a busy lock can't hide a pin
+pub fn pinned_mods() -> Vec<String> {
+    R4D_CFG
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .pinned_mods
+        .clone()
+}
+
+pub fn get_provenance(module: &DivaMod) -> Option<ModProvenance> {
+    let dir = module.dir_name()?;
+    match R4D_CFG.try_lock() {
+        Ok(cfg) => cfg.provenance.get(&dir).cloned(),
+        Err(_) => None,
+    }
+}
+
+fn escape_markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace('\n', " ")
+}
+
+fn escape_csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Formats the mod list as either a markdown table or csv using the chosen columns
+pub fn format_mod_list(mods: &Vec<DivaMod>, opts: &ModListExport) -> String {
+    let mut headers = vec![];
+    if opts.name {
+        headers.push("Name");
+    }
+    if opts.enabled {
+        headers.push("Enabled");
+    }
+    if opts.author {
+        headers.push("Author");
+    }
+    if opts.version {
+        headers.push("Version");
+    }
+    if opts.link {
+        headers.push("GameBanana");
+    }
+    if headers.is_empty() {
+        return String::new();
+    }
+
+    let mut rows = vec![];
+    for m in mods {
+        let element = DivaModElement::from(m.clone());
+        let link = match get_provenance(m) {
+            Some(p) if p.gb_id > 0 => format!("https://gamebanana.com/mods/{}", p.gb_id),
+            _ => String::new(),
+        };
+        let mut row = vec![];
+        if opts.name {
+            row.push(element.name.to_string());
+        }
+        if opts.enabled {
+            row.push(element.enabled.to_string());
+        }
+        if opts.author {
+            row.push(element.author.to_string());
+        }
+        if opts.version {
+            row.push(element.version.to_string());
+        }
+        if opts.link {
+            row.push(link);
+        }
+        rows.push(row);
+    }
+
+    let mut out = String::new();
+    if opts.markdown {
+        out += &format!("| {} |\n", headers.join(" | "));
+        out += &format!("|{}\n", " --- |".repeat(headers.len()));
+        for row in rows {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    if headers[i] == "GameBanana" && !cell.is_empty() {
+                        format!("[View]({cell})")
+                    } else {
+                        escape_markdown_cell(cell)
+                    }
+                })
+                .collect();
+            out += &format!("| {} |\n", cells.join(" | "));
+        }
+    } else {
+        out += &format!("{}\n", headers.join(","));
+        for row in rows {
+            let cells: Vec<String> = row.iter().map(|c| escape_csv_cell(c)).collect();
+            out += &format!("{}\n", cells.join(","));
+        }
+    }
+    out
+}
+
 pub fn is_dml_installed_at(dir: &String) -> bool {
     let mut buf = PathBuf::from(dir);
     buf.push("dinput8.dll");
     buf.exists()
 }
 
+/// Points Rust4Diva at a mods folder, relative paths are inside the game folder so "mods" is the
+/// game's own folder. DML's config is left alone so its own folder is still known once the override
+/// is cleared
+pub fn use_mods_dir(dir: &str) {
+    if let Ok(mut mods_dir) = MODS_DIR.lock() {
+        *mods_dir = dir.to_owned();
+    }
+}
+
+/// Whether `mods_dir` is a folder, relative to `diva_dir` unless it's absolute. The casing doesn't
+/// have to match, see find_mods_dir
+pub fn mods_dir_exists(diva_dir: &str, mods_dir: &str) -> bool {
+    resolve_path_casing(&Path::new(diva_dir).join(mods_dir)).is_some_and(|p| p.is_dir())
+}
+
+/// Switches to another game folder, DivaModLoader's config and the mods folder are read from it again
+pub fn use_diva_dir(dir: &str, configured_mods_dir: &str) {
+    if let Ok(mut diva_dir) = DIVA_DIR.lock() {
+        *diva_dir = dir.to_owned();
+    }
+    if let Ok(mut dml) = DML_CFG.lock() {
+        *dml = load_diva_ml_config(dir).unwrap_or(DivaModLoader::new());
+    }
+    init_mods_dir(configured_mods_dir);
+}
+
+/// Uses the mods folder set in the config, or DivaModLoader's own when none is set or the configured
+/// folder doesn't exist
+pub fn init_mods_dir(configured: &str) {
+    if !configured.is_empty() {
+        let diva_dir = DIVA_DIR.lock().map(|d| d.clone()).unwrap_or_default();
+        if mods_dir_exists(&diva_dir, configured) {
+            use_mods_dir(configured);
+            return;
+        }
+        eprintln!("Mods folder {configured} does not exist, using DivaModLoader's");
+    }
+    let dml_dir = match DML_CFG.lock() {
+        Ok(dml) if !dml.mods.is_empty() => dml.mods.clone(),
+        _ => "mods".to_owned(),
+    };
+    if let Ok(mut mods_dir) = MODS_DIR.lock() {
+        *mods_dir = dml_dir;
+    }
+}
+
+/// The mods folder of the current game folder
+pub fn current_mods_dir() -> Option<PathBuf> {
+    let dir = DIVA_DIR.lock().ok()?.clone();
+    let mods_dir = MODS_DIR.lock().ok()?.clone();
+    Some(find_mods_dir(Path::new(&dir), &mods_dir))
+}
+
+/// Finds the mods folder, configs made on Windows can use a different casing than the folder has on
+/// a case sensitive filesystem like the Steam Deck's. The casing found on disk is stored from then on
+fn find_mods_dir(diva_dir: &Path, mods_dir: &str) -> PathBuf {
+    let wanted = diva_dir.join(mods_dir);
+    let Some(found) = resolve_path_casing(&wanted) else {
+        return wanted;
+    };
+    if found == wanted {
+        return found;
+    }
+    println!(
+        "Mods folder {} does not exist, using {} instead",
+        wanted.display(),
+        found.display()
+    );
+    let corrected = match found.strip_prefix(diva_dir) {
+        Ok(relative) if Path::new(mods_dir).is_relative() => relative.display().to_string(),
+        _ => found.display().to_string(),
+    };
+    use_mods_dir(&corrected);
+    if let Ok(mut cfg) = R4D_CFG.try_lock() {
+        if !cfg.mods_dir.is_empty() {
+            cfg.mods_dir = corrected;
+        }
+    }
+    found
+}
+
+/// Counts the folders in `dir` that have a mod config, used to check a mods folder before using it
+pub fn count_mod_folders(dir: &Path) -> usize {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|e| e.path().join("config.toml").is_file())
+            .count(),
+        Err(_) => 0,
+    }
+}
+
 pub fn is_dml_installed() -> bool {
     return match get_diva_folder() {
         Some(dir) => is_dml_installed_at(&dir),
@@ -891,6 +2348,10 @@ static DML_LATEST_RELEASE: &'static str =
 pub struct GhRelease {
     name: String,
     assets: Vec<GhReleaseAsset>,
+    #[serde(default)]
+    pub tag_name: String,
+    #[serde(default)]
+    pub html_url: String,
 }
 
 unsafe impl Send for GhRelease {}
@@ -930,3 +2391,89 @@ pub async fn get_latest_dml() -> Result<GhRelease, Box<dyn Error + Send + Sync>>
         .await?;
     Ok(sonic_rs::from_str::<GhRelease>(&text)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_dir;
+
+    fn entries(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn single_root_folder_is_unwrapped() {
+        let archive = entries(&[
+            "Pack v2/",
+            "Pack v2/First Mod/",
+            "Pack v2/First Mod/config.toml",
+            "Pack v2/First Mod/rom/mod_pv_db.txt",
+            "Pack v2/Second Mod/config.toml",
+        ]);
+        assert_eq!(
+            archive_layout(&archive),
+            ArchiveLayout::Wrapped("Pack v2".to_owned())
+        );
+    }
+
+    #[test]
+    fn loose_files_are_wrapped() {
+        let archive = entries(&["config.toml", "rom/", "rom/mod_pv_db.txt", "readme.txt"]);
+        assert_eq!(archive_layout(&archive), ArchiveLayout::LooseFiles);
+        // without a config.toml anything besides folders at the root means loose files
+        let archive = entries(&["rom/mod_pv_db.txt", "readme.txt"]);
+        assert_eq!(archive_layout(&archive), ArchiveLayout::LooseFiles);
+    }
+
+    #[test]
+    fn mod_folders_are_kept() {
+        let archive = entries(&["First Mod/config.toml", "Second Mod/config.toml"]);
+        assert_eq!(archive_layout(&archive), ArchiveLayout::ModFolders);
+        // a single mod folder is a mod, not a wrapper
+        let archive = entries(&["Only Mod/config.toml", "Only Mod/rom/mod_pv_db.txt"]);
+        assert_eq!(archive_layout(&archive), ArchiveLayout::ModFolders);
+    }
+
+    #[test]
+    fn rar_and_macos_entries() {
+        // rar lists folders without a trailing slash and uses backslashes
+        let archive = entries(&["Mod", "Mod\\config.toml", "Mod\\rom\\mod_pv_db.txt"]);
+        assert_eq!(archive_layout(&archive), ArchiveLayout::ModFolders);
+        let archive = entries(&[
+            "__MACOSX/",
+            "__MACOSX/._config.toml",
+            "config.toml",
+            "rom/mod_pv_db.txt",
+        ]);
+        assert_eq!(archive_layout(&archive), ArchiveLayout::LooseFiles);
+    }
+
+    #[test]
+    fn loose_files_keep_the_folder_of_an_existing_install() {
+        let mods_dir = test_dir("loose");
+        let archive = Path::new("/downloads/Cool Mod.zip");
+        assert_eq!(loose_folder_name(archive, &mods_dir, false), "Cool Mod.zip");
+        assert_eq!(loose_folder_name(archive, &mods_dir, true), "Cool Mod");
+
+        fs::create_dir(mods_dir.join("Cool Mod.zip")).unwrap();
+        assert_eq!(loose_folder_name(archive, &mods_dir, true), "Cool Mod.zip");
+        let _ = fs::remove_dir_all(mods_dir);
+    }
+
+    #[test]
+    fn mods_folder_overrides_have_to_exist() {
+        let diva_dir = test_dir("override");
+        fs::create_dir(diva_dir.join("Mods")).unwrap();
+        fs::write(diva_dir.join("file"), "").unwrap();
+        let diva = diva_dir.display().to_string();
+        assert!(mods_dir_exists(&diva, "Mods"));
+        assert!(mods_dir_exists(&diva, "mods"));
+        assert!(mods_dir_exists(
+            &diva,
+            &diva_dir.join("Mods").display().to_string()
+        ));
+        assert!(!mods_dir_exists(&diva, "other"));
+        assert!(!mods_dir_exists(&diva, "file"));
+        let _ = fs::remove_dir_all(diva_dir);
+    }
+}
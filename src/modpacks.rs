@@ -1,26 +1,45 @@
 use base64ct::{Base64, Encoding};
 use filenamify::filenamify;
+use futures_util::StreamExt;
 use sha2::{Digest, Sha256};
 use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel};
 use sonic_rs::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
 use std::vec;
 use tokio::fs;
 use toml_edit::value;
 
-use crate::config::{write_config, write_config_sync, write_dml_config};
-use crate::diva::{get_config_dir, get_diva_folder, open_error_window};
-use crate::modmanagement::{get_mods_in_order, save_mod_config, DivaMod};
+use crate::config::{write_config, write_config_sync, write_dml_config, DivaConfig};
+use crate::diva::{
+    ask_choice, ask_confirmation, get_config_dir, get_diva_folder, open_error_window, DialogAnswer,
+};
+use crate::gamebanana::{
+    create_loadout_url, download_loadout_mods, fetch_source_file, max_downloads, SharedLoadout,
+    SharedLoadoutMod,
+};
+use crate::modmanagement::{
+    conflicting_mods, current_mods_dir, get_mods, get_mods_in_order, get_pack_entries_in_order,
+    get_provenance, mark_mods_used, save_mod_config, scan_conflicts, set_mods_table, DivaMod,
+    PackEntry,
+};
 use crate::slint_generatedApp::App;
+use crate::undo::{record_action, ModAction};
+use crate::util::fuzzy_score;
 use crate::{
-    ConfirmDeletePack, DivaModElement, ModpackLogic, WindowLogic, DML_CFG, MODS, MOD_PACKS, R4D_CFG,
+    ConfirmDeletePack, DivaModElement, ModState, ModpackLogic, WindowLogic, DML_CFG, MODS,
+    MOD_PACKS, R4D_CFG,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ModPack {
     pub name: String,
     pub mods: Vec<ModPackMod>,
+    /// File the mods still have to be read from, packs listed from the index are only read once used
+    #[serde(skip)]
+    pub(crate) unread_file: Option<String>,
 }
 
 // impl ModPAc
@@ -77,11 +96,16 @@ impl PartialEq<ModPackMod> for DivaMod {
 
 impl ModPackMod {
     pub fn to_element(self: &Self) -> DivaModElement {
-        if let Ok(mods) = MODS.try_lock() {
-            if let Some(m) = mods.get(&self.dir_name().unwrap_or_default()) {
-                return m.clone().into();
-            }
+        // the lock is released before converting, the conversion checks the other mods too
+        let module = MODS
+            .try_lock()
+            .ok()
+            .and_then(|mods| mods.get(&self.dir_name().unwrap_or_default()).cloned());
+        if let Some(m) = module {
+            return m.into();
         }
+        // the folder is gone so its name can only be taken from the path the pack remembers
+        let dirname = self.folder_name();
         DivaModElement {
             author: SharedString::from(""),
             name: self.name.clone().into(),
@@ -89,10 +113,25 @@ impl ModPackMod {
             description: SharedString::from(""),
             version: SharedString::from(""),
             path: self.path.clone().into(),
-            dirname: SharedString::from(self.dir_name().unwrap_or("".to_string())),
+            pinned: false,
+            size: -1,
+            dirname: dirname.into(),
+            dependencies: SharedString::new(),
+            dependency_issues: SharedString::new(),
+            last_used: SharedString::new(),
+            state: ModState::Missing,
         }
     }
 
+    /// Name of the folder the mod was in when it was added, this is known even if it's gone now
+    pub fn folder_name(self: &Self) -> String {
+        PathBuf::from(&self.path)
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|dir| dir.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
     pub fn dir_name(self: &Self) -> Option<String> {
         let mut buf = PathBuf::from(self.path.to_string().clone());
         buf.pop();
@@ -106,11 +145,51 @@ impl ModPackMod {
     }
 }
 
+/// Sets whether `module` is enabled in a pack or the priority list. It's matched by folder so mods
+/// sharing a name can't flip each other, only the flag changes and the mod keeps its place
+pub fn set_mod_enabled(mods: &mut [ModPackMod], module: &DivaMod, enabled: bool) -> bool {
+    match mods.iter_mut().find(|p| **p == *module) {
+        Some(entry) => {
+            entry.enabled = enabled;
+            true
+        }
+        None => false,
+    }
+}
+
+/// A pack as it is written by export, mods are referenced by their folder name instead of a path so
+/// the file can be imported on another machine
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExportedPack {
+    pub name: String,
+    pub mods: Vec<ExportedPackMod>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExportedPackMod {
+    pub name: String,
+    pub folder: String,
+    pub enabled: bool,
+    /// where the mod was downloaded from, only known for mods installed through Rust4Diva
+    #[serde(default)]
+    pub source: Option<SharedLoadoutMod>,
+}
+
+/// Search term and quick filter of the applied pack's mods list, the filter is 0 for all mods, 1
+/// for enabled, 2 for disabled, 3 for conflicting and 4 for missing mods
+static PACK_FILTER: Mutex<(String, i32)> = Mutex::new((String::new(), 0));
+
+/// Load order of each pack from before it was first reordered this session, the global priority
+/// is stored under an empty name
+static SESSION_ORDERS: LazyLock<Mutex<HashMap<String, Vec<ModPackMod>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
 impl ModPack {
     pub fn new(name: String) -> Self {
         Self {
             name: name.clone(),
             mods: Vec::new(),
+            unread_file: None,
         }
     }
 }
@@ -193,18 +272,21 @@ pub async fn init(ui: &App) {
 
     let weak = ui.as_weak();
     ui.global::<ModpackLogic>().on_set_search(move |term| {
-        let term = term.to_string();
-        let mods = get_mods_in_order();
-        let filtered: Vec<DivaModElement> = mods
-            .iter()
-            .cloned()
-            .filter(|m| m.search(&term))
-            .map(|m| m.into())
-            .collect();
-        let vecmod = VecModel::from(filtered);
-        weak.unwrap().set_pack_mods(ModelRc::new(vecmod));
+        if let Ok(mut filter) = PACK_FILTER.lock() {
+            filter.0 = term.to_string();
+        }
+        refresh_pack_mods(weak.clone());
     });
 
+    let weak = ui.as_weak();
+    ui.global::<ModpackLogic>()
+        .on_set_status_filter(move |status| {
+            if let Ok(mut filter) = PACK_FILTER.lock() {
+                filter.1 = status;
+            }
+            refresh_pack_mods(weak.clone());
+        });
+
     ui.global::<ModpackLogic>()
         .on_change_modpack(move |mod_pack| {
             #[cfg(debug_assertions)]
@@ -212,6 +294,7 @@ pub async fn init(ui: &App) {
             let ui_change_handle = ui_change_handle.clone();
             // make sure that the mutex is unlocked after we are done with it
             let mut pack = ModPack::new("All Mods".to_owned());
+            let mut missing = vec![];
             #[cfg(debug_assertions)]
             println!("Locking CFG @ modpacks.rs::on_change_modpack()");
             {
@@ -222,6 +305,15 @@ pub async fn init(ui: &App) {
                         return;
                     }
                 };
+                if !is_same_pack(&cfg.applied_pack, &mod_pack) {
+                    record_action(ModAction::ApplyPack {
+                        from: cfg.applied_pack.clone(),
+                        to: mod_pack.to_string(),
+                    });
+                    let names = enabled_pack_mods(&cfg, &mod_pack);
+                    mark_mods_used(&mut cfg, names);
+                    missing = missing_pack_mods(&mod_pack);
+                }
                 cfg.applied_pack = mod_pack.to_string();
                 pack.mods = cfg.priority.clone();
                 if write_config_sync(cfg.clone()).is_err() {
@@ -247,18 +339,9 @@ pub async fn init(ui: &App) {
                     Ok(ms) => ms,
                     Err(_) => return,
                 };
-                match packs.get_mut(&mod_pack.to_string()) {
-                    Some(p) => {
-                        if p.mods.len() != mods.len() {
-                            p.mods = mods.iter().map(|m| ModPackMod::from(m.clone())).collect();
-                            match save_modpack_sync(p.clone()) {
-                                Err(e) => eprintln!("{e}"),
-                                _ => {}
-                            }
-                        }
-                        pack = p.clone();
-                    }
-                    None => {}
+                // mods the pack lists that aren't installed are kept so they show up as missing
+                if let Some(p) = loaded_pack(&mut packs, &mod_pack.to_string()) {
+                    pack = p.clone();
                 }
                 for m in mods.iter_mut() {
                     if let Some(pm) = pack.mods.iter().find(|p| p.path == m.path) {
@@ -296,6 +379,15 @@ pub async fn init(ui: &App) {
             }
 
             ui.global::<ModpackLogic>().invoke_apply_modpack(model);
+            // the whole pack has been applied, the shown list can be filtered again now
+            refresh_pack_mods(ui_change_handle.clone());
+            ui.set_order_changes(order_changes().map_or(0, |(_, moved)| moved.len() as i32));
+            if !missing.is_empty() {
+                open_error_window(format!(
+                    "These mods of {mod_pack} aren't installed and were left out:\n{}",
+                    missing.join("\n")
+                ));
+            }
         });
 
     ui.global::<ModpackLogic>().on_create_new_pack(move |pack| {
@@ -303,12 +395,23 @@ pub async fn init(ui: &App) {
 
         let pack_name = filenamify(pack.to_string());
 
-        if pack_name.len() <= 0 || pack_name == "All Mods" {
+        if let Err(e) = pack_file_name(&pack_name) {
+            open_error_window(e.to_string());
+            return;
+        }
+        if pack_name == "All Mods" {
             return;
         }
         let modpack = ModPack::new(pack_name.clone());
         let mut gpacks = MOD_PACKS.lock().unwrap();
         if !gpacks.contains_key(&pack_name) {
+            // a new pack never takes over the file of a pack saved under a different name
+            if pack_file_exists(&pack_name) {
+                open_error_window(format!(
+                    "A modpack is already saved as {pack_name}.json, pick a different name"
+                ));
+                return;
+            }
             gpacks.insert(pack_name.clone(), modpack);
             let ui = ui_add_pack_handle.upgrade().unwrap();
             let binding = ui.get_modpacks();
@@ -318,6 +421,83 @@ pub async fn init(ui: &App) {
             }
         }
     });
+    let weak = ui.as_weak();
+    ui.global::<ModpackLogic>().on_copy_share_link(move |pack_name| {
+        let pack = match MOD_PACKS.try_lock() {
+            Ok(mut packs) => match loaded_pack(&mut packs, &pack_name.to_string()) {
+                Some(pack) => pack.clone(),
+                None => return,
+            },
+            Err(_) => return,
+        };
+        let installed = get_mods();
+        let mut mods = vec![];
+        let mut missing = vec![];
+        for m in pack.mods {
+            match installed.iter().find(|d| m == **d).and_then(get_provenance) {
+                Some(p) => mods.push(SharedLoadoutMod {
+                    id: p.gb_id,
+                    file: p.file_id,
+                }),
+                None => missing.push(m.name),
+            }
+        }
+        let loadout = SharedLoadout {
+            name: pack.name,
+            mods,
+        };
+        match create_loadout_url(&loadout) {
+            Ok(url) => weak.unwrap().invoke_copy_to_clipboard(url.into()),
+            Err(e) => {
+                open_error_window(e.to_string());
+                return;
+            }
+        }
+        if !missing.is_empty() {
+            open_error_window(format!(
+                "These mods weren't downloaded through Rust4Diva so they were left out of the link:\n{}",
+                missing.join("\n")
+            ));
+        }
+    });
+
+    let weak = ui.as_weak();
+    ui.global::<ModpackLogic>()
+        .on_export_download_links(move |pack_name| {
+            tokio::spawn(export_download_links(pack_name.to_string(), weak.clone()));
+        });
+
+    ui.global::<ModpackLogic>()
+        .on_export_modpack(move |pack_name| {
+            tokio::spawn(export_modpack(pack_name.to_string()));
+        });
+
+    let weak = ui.as_weak();
+    ui.global::<ModpackLogic>().on_import_modpack(move || {
+        tokio::spawn(import_modpack(weak.clone()));
+    });
+
+    let weak = ui.as_weak();
+    ui.global::<ModpackLogic>()
+        .on_rename_modpack(move |old, new| {
+            let old = old.to_string();
+            match rename_modpack(&old, &filenamify(new.to_string())) {
+                Ok(new) => {
+                    let ui = weak.unwrap();
+                    if ui.get_active_pack().to_string() == old {
+                        ui.set_active_pack(new.clone().into());
+                    }
+                    set_pack_list(&ui);
+                }
+                Err(e) => open_error_window(e.to_string()),
+            }
+        });
+
+    let weak = ui.as_weak();
+    ui.global::<ModpackLogic>().on_show_order_changes(move || {
+        tokio::spawn(show_order_changes(weak.clone()));
+    });
+
     let weak = ui.as_weak();
     ui.global::<ModpackLogic>()
         .on_save_modpack(move |pack_name, mods| {
@@ -334,12 +514,12 @@ pub async fn init(ui: &App) {
                 Ok(packs) => packs,
                 Err(_) => return,
             };
-            let modpack = match packs.get_mut(&pack_name) {
+            let modpack = match loaded_pack(&mut packs, &pack_name) {
                 Some(pack) => pack,
                 None => return,
             };
             modpack.mods = vec;
-            match save_modpack_sync(modpack.clone()) {
+            match save_modpack_sync(modpack.clone(), ExistingPack::Overwrite) {
                 Ok(_) => {
                     let ui = weak.unwrap();
                     ui.global::<ModpackLogic>().invoke_apply_modpack(mods);
@@ -430,32 +610,22 @@ pub async fn init(ui: &App) {
                     if let Ok(mut packs) = MOD_PACKS.lock() {
                         match packs.remove(&packname.to_string()) {
                             Some(pack) => {
-                                let mut packsvec: Vec<ModPack> = vec![];
-                                for p in packs.values() {
-                                    packsvec.push(p.clone());
-                                }
                                 let ui_delete_handle = ui_delete_handle.clone();
                                 tokio::spawn(async move {
-                                    let mut buf = match get_modpacks_folder() {
-                                        Ok(buf) => buf,
-                                        Err(e) => {
-                                            eprintln!("{e}");
-                                            return;
-                                        }
-                                    };
-                                    buf.push(format!("{}.json", filenamify(pack.name)));
-                                    match fs::remove_file(buf).await {
+                                    match delete_modpack(&pack.name).await {
                                         Ok(_) => {
-                                            let _ = ui_delete_handle.clone().upgrade_in_event_loop(
-                                                move |ui| {
-                                                    let vec_mod: VecModel<SharedString> =
-                                                        VecModel::default();
-                                                    for p in packsvec {
-                                                        vec_mod.push(p.name.clone().into());
+                                            let _ =
+                                                ui_delete_handle.upgrade_in_event_loop(move |ui| {
+                                                    // only the pack is gone, its mods stay as they are
+                                                    if ui.get_active_pack().to_string() == pack.name
+                                                    {
+                                                        ui.global::<ModpackLogic>()
+                                                            .invoke_change_modpack(
+                                                                "All Mods".into(),
+                                                            );
                                                     }
-                                                    ui.set_modpacks(ModelRc::new(vec_mod));
-                                                },
-                                            );
+                                                    set_pack_list(&ui);
+                                                });
                                         }
                                         Err(e) => {
                                             let msg = format!(
@@ -484,22 +654,167 @@ pub async fn init(ui: &App) {
     }
 }
 
+/// A pack listed in the modpacks index
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PackIndexEntry {
+    name: String,
+    file: String,
+    mods: usize,
+}
+
+/// Serializes changes to the index so saves running at the same time can't drop each other's entry
+static PACK_INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// The index is kept next to the modpacks folder so it is never mistaken for a pack
+fn pack_index_path() -> std::io::Result<PathBuf> {
+    Ok(get_modpacks_folder()?.with_extension("json"))
+}
+
+fn read_pack_index() -> Option<Vec<PackIndexEntry>> {
+    let text = std::fs::read_to_string(pack_index_path().ok()?).ok()?;
+    sonic_rs::from_str(&text).ok()
+}
+
+fn write_pack_index(index: &Vec<PackIndexEntry>) -> std::io::Result<()> {
+    let path = pack_index_path()?;
+    let text = sonic_rs::to_string_pretty(index)
+        .map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string()))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, text)?;
+    std::fs::rename(tmp, path)
+}
+
+/// Adds or replaces a pack's entry in the index, `mods` is None when `name` was deleted. A missing
+/// index is left missing, the next load rebuilds it from the folder
+fn update_pack_index(name: &str, mods: Option<usize>) -> std::io::Result<()> {
+    let _guard = PACK_INDEX_LOCK.lock();
+    let Some(mut index) = read_pack_index() else {
+        return Ok(());
+    };
+    index.retain(|e| e.name != name);
+    if let Some(mods) = mods {
+        index.push(PackIndexEntry {
+            name: name.to_owned(),
+            file: pack_file_name(name)?,
+            mods,
+        });
+    }
+    write_pack_index(&index)
+}
+
+/// Leftovers of a save that was interrupted are skipped, the pack file itself is still whole
+fn is_pack_file(path: &Path) -> bool {
+    !path.is_dir() && !path.extension().is_some_and(|e| e == "tmp")
+}
+
+/// Lists the packs in the index without reading their files. None if there is no index or the files
+/// it lists aren't the ones in the folder, so packs added or removed by hand are picked up
+fn load_indexed_packs(packs_dir: &Path) -> Option<HashMap<String, ModPack>> {
+    let index = read_pack_index()?;
+    let mut files = vec![];
+    for entry in std::fs::read_dir(packs_dir).ok()? {
+        let entry = entry.ok()?;
+        if is_pack_file(&entry.path()) {
+            files.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    let mut listed: Vec<String> = index.iter().map(|e| e.file.clone()).collect();
+    files.sort();
+    listed.sort();
+    if files != listed {
+        return None;
+    }
+    let mut packs = HashMap::new();
+    for entry in index {
+        packs.insert(
+            entry.name.clone(),
+            ModPack {
+                name: entry.name,
+                mods: Vec::new(),
+                unread_file: Some(entry.file),
+            },
+        );
+    }
+    Some(packs)
+}
+
+fn read_pack_file(file: &Path) -> std::io::Result<ModPack> {
+    let lines = std::fs::read_to_string(file)?;
+    Ok(sonic_rs::from_str(lines.as_str())?)
+}
+
+/// Gets a pack with its mods, its file is read first if it was only listed from the index. None if
+/// there is no such pack or its file can't be read
+pub fn loaded_pack<'a>(
+    packs: &'a mut HashMap<String, ModPack>,
+    name: &str,
+) -> Option<&'a mut ModPack> {
+    let pack = packs.get_mut(name)?;
+    if let Some(file) = &pack.unread_file {
+        match get_modpacks_folder().and_then(|dir| read_pack_file(&dir.join(file))) {
+            Ok(read) => {
+                pack.mods = read.mods;
+                pack.unread_file = None;
+            }
+            Err(e) => {
+                eprintln!("Unable to read modpack {name}: {e}");
+                return None;
+            }
+        }
+    }
+    Some(pack)
+}
+
+/// Reads the files of every pack that was only listed from the index
+pub fn load_all_packs(packs: &mut HashMap<String, ModPack>) {
+    let unread: Vec<String> = packs
+        .values()
+        .filter(|p| p.unread_file.is_some())
+        .map(|p| p.name.clone())
+        .collect();
+    for name in unread {
+        loaded_pack(packs, &name);
+    }
+}
+
+/// Loads every pack of the current game folder. When the index is up to date the packs are only
+/// listed from it and read when first used, otherwise the folder is scanned and the index is written
+/// again from what was found
 pub async fn load_mod_packs() -> std::io::Result<HashMap<String, ModPack>> {
     let packs_dir = get_modpacks_folder();
     if packs_dir.is_err() {
         return Err(packs_dir.unwrap_err().into());
     }
-    let mut packs: HashMap<String, ModPack> = HashMap::new();
     let packs_dir = packs_dir.unwrap();
+    if let Some(packs) = load_indexed_packs(&packs_dir) {
+        return Ok(packs);
+    }
+    println!(
+        "Modpack index is missing or out of date, scanning {}",
+        packs_dir.display()
+    );
+    let mut packs: HashMap<String, ModPack> = HashMap::new();
+    let mut index = vec![];
     for entry in std::fs::read_dir(packs_dir.clone())? {
         let entry = entry?;
-        if entry.path().is_dir() {
+        if !is_pack_file(&entry.path()) {
             continue;
         }
         let lines = fs::read_to_string(entry.path()).await?;
         let pack: ModPack = sonic_rs::from_str(lines.as_str())?;
+        index.push(PackIndexEntry {
+            name: pack.name.clone(),
+            file: entry.file_name().to_string_lossy().to_string(),
+            mods: pack.mods.len(),
+        });
         packs.insert(pack.name.clone(), pack);
     }
+    {
+        let _guard = PACK_INDEX_LOCK.lock();
+        if let Err(e) = write_pack_index(&index) {
+            eprintln!("Unable to write the modpack index: {e}");
+        }
+    }
 
     Ok(packs)
 }
@@ -525,37 +840,765 @@ pub fn get_modpacks_folder() -> std::io::Result<PathBuf> {
     }
 }
 
-pub async fn save_modpack(pack: ModPack) -> std::io::Result<()> {
+/// Name of the file a pack is stored in, names that are empty or only whitespace once the characters
+/// a file name can't have are replaced are refused so a pack can't end up as ".json"
+pub fn pack_file_name(name: &str) -> std::io::Result<String> {
+    let file = filenamify(name);
+    if file.trim().is_empty() {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("\"{name}\" can't be used as a modpack name, it needs at least one character that isn't whitespace"),
+        ));
+    }
+    Ok(file + ".json")
+}
+
+/// What to do when the file a pack is saved to already exists, names that only differ in characters
+/// a file name can't have share a file so this can be a different pack
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExistingPack {
+    Overwrite,
+    Refuse,
+}
+
+/// Fails with [`ErrorKind::AlreadyExists`] if the file is there and overwriting wasn't asked for
+fn check_existing_pack(file: &PathBuf, existing: ExistingPack) -> std::io::Result<()> {
+    if existing == ExistingPack::Refuse && file.exists() {
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!(
+                "A modpack is already saved as {}",
+                file.file_name().unwrap_or_default().to_string_lossy()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// A pack that was only listed from the index has no mods yet, saving it would empty its file
+fn check_pack_read(pack: &ModPack) -> std::io::Result<()> {
+    if pack.unread_file.is_some() {
+        return Err(std::io::Error::new(
+            ErrorKind::Other,
+            format!("Modpack {} hasn't been read yet", pack.name),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a pack with this name would replace an existing pack file when saved
+pub fn pack_file_exists(name: &str) -> bool {
+    match (get_modpacks_folder(), pack_file_name(name)) {
+        (Ok(dir), Ok(file)) => dir.join(file).exists(),
+        _ => false,
+    }
+}
+
+/// Saves a pack to its own file, it is written next to the old one first and then moved over it so
+/// a save that gets interrupted never leaves a half written pack behind
+pub async fn save_modpack(pack: ModPack, existing: ExistingPack) -> std::io::Result<()> {
+    check_pack_read(&pack)?;
+    let mut packs_dir = get_modpacks_folder()?;
+    packs_dir.push(pack_file_name(&pack.name)?);
+    check_existing_pack(&packs_dir, existing)?;
+    let (name, mods) = (pack.name.clone(), pack.mods.len());
+    // serializing a pack with hundreds of mods shouldn't hold up the other tasks
+    let pckstr = tokio::task::spawn_blocking(move || sonic_rs::to_string_pretty(&pack))
+        .await
+        .map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string()))?;
+    if let Ok(pckstr) = pckstr {
+        let tmp = packs_dir.with_extension("json.tmp");
+        fs::write(&tmp, pckstr).await?;
+        fs::rename(tmp, packs_dir).await?;
+        return update_pack_index(&name, Some(mods));
+    }
+    Ok(())
+}
+pub fn save_modpack_sync(pack: ModPack, existing: ExistingPack) -> std::io::Result<()> {
+    check_pack_read(&pack)?;
     match get_modpacks_folder() {
         Ok(mut packs_dir) => {
-            packs_dir.push(filenamify(pack.name.clone()) + ".json");
+            packs_dir.push(pack_file_name(&pack.name)?);
+            check_existing_pack(&packs_dir, existing)?;
             if let Ok(pckstr) = sonic_rs::to_string_pretty(&pack.clone()) {
-                return fs::write(packs_dir, pckstr).await;
+                let tmp = packs_dir.with_extension("json.tmp");
+                std::fs::write(&tmp, pckstr)?;
+                std::fs::rename(tmp, packs_dir)?;
+                return update_pack_index(&pack.name, Some(pack.mods.len()));
             }
             Ok(())
         }
         Err(e) => Err(e.into()),
     }
 }
-pub fn save_modpack_sync(pack: ModPack) -> std::io::Result<()> {
-    match get_modpacks_folder() {
-        Ok(mut packs_dir) => {
-            packs_dir.push(filenamify(pack.name.clone()) + ".json");
-            if let Ok(pckstr) = sonic_rs::to_string_pretty(&pack.clone()) {
-                return std::fs::write(packs_dir, pckstr);
+
+/// Saves several packs a few at a time, `progress` gets the number of packs saved so far and the
+/// total after each one. Every pack has its own file so they can't overwrite each other
+pub async fn save_modpacks(
+    packs: Vec<ModPack>,
+    existing: ExistingPack,
+    progress: impl Fn(usize, usize),
+) -> std::io::Result<()> {
+    let total = packs.len();
+    let mut saves =
+        futures_util::stream::iter(packs.into_iter().map(|p| save_modpack(p, existing)))
+            .buffer_unordered(4);
+    let mut done = 0;
+    let mut res = Ok(());
+    while let Some(saved) = saves.next().await {
+        done += 1;
+        progress(done, total);
+        if let Err(e) = saved {
+            res = Err(e);
+        }
+    }
+    res
+}
+
+/// Removes a modpack's file from the modpacks folder
+pub async fn delete_modpack(name: &String) -> std::io::Result<()> {
+    let mut buf = get_modpacks_folder()?;
+    buf.push(pack_file_name(name)?);
+    fs::remove_file(buf).await?;
+    update_pack_index(name, None)
+}
+
+/// Appends mods to the end of a pack if they aren't already in it and saves the pack
+pub fn add_mods_to_pack(pack_name: &String, mods: Vec<DivaMod>) -> std::io::Result<()> {
+    let mut packs = match MOD_PACKS.lock() {
+        Ok(packs) => packs,
+        Err(e) => return Err(std::io::Error::new(ErrorKind::Other, e.to_string())),
+    };
+    let pack = match loaded_pack(&mut packs, pack_name) {
+        Some(pack) => pack,
+        None => {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                format!("Modpack {pack_name} does not exist"),
+            ))
+        }
+    };
+    for m in mods {
+        let packmod = ModPackMod::from(m);
+        if !pack.mods.contains(&packmod) {
+            pack.mods.push(packmod);
+        }
+    }
+    save_modpack_sync(pack.clone(), ExistingPack::Overwrite)
+}
+
+/// Looks up the current download url of every mod in the pack that came from GameBanana and copies
+/// them to the clipboard or saves them to a text file, one `name: url` line per mod
+async fn export_download_links(pack_name: String, weak: slint::Weak<App>) {
+    let pack = match MOD_PACKS.try_lock() {
+        Ok(mut packs) => match loaded_pack(&mut packs, &pack_name) {
+            Some(pack) => pack.clone(),
+            None => return,
+        },
+        Err(_) => return,
+    };
+    let installed = get_mods();
+    let mut sources = vec![];
+    let mut skipped = vec![];
+    for m in pack.mods {
+        match installed.iter().find(|d| m == **d).and_then(get_provenance) {
+            Some(p) => sources.push((m.name, p)),
+            None => skipped.push(m.name),
+        }
+    }
+    // buffered keeps the pack's order while a few lookups run at once
+    let results: Vec<_> =
+        futures_util::stream::iter(sources.into_iter().map(|(name, provenance)| async move {
+            let res = fetch_source_file(&provenance).await;
+            (name, res)
+        }))
+        .buffered(max_downloads())
+        .collect()
+        .await;
+    let mut lines = vec![];
+    for (name, res) in results {
+        match res {
+            Ok(file) => lines.push(format!("{name}: {}", file.download_url)),
+            Err(e) => skipped.push(format!("{name} ({e})")),
+        }
+    }
+    if lines.is_empty() {
+        open_error_window(format!(
+            "None of the mods in {pack_name} have a download link on GameBanana"
+        ));
+        return;
+    }
+    let mut msg = format!("Found download links for {} mods.", lines.len());
+    if !skipped.is_empty() {
+        msg += &format!(
+            "\n\nThese mods weren't downloaded through Rust4Diva or are no longer available:\n{}",
+            skipped.join("\n")
+        );
+    }
+    let answer = ask_choice(
+        "Download Links".to_owned(),
+        msg,
+        Some("Copy to Clipboard".to_owned()),
+        Some("Save to File".to_owned()),
+    )
+    .await;
+    let text = lines.join("\n");
+    match answer {
+        DialogAnswer::Confirm => {
+            let _ = weak.upgrade_in_event_loop(move |ui| ui.invoke_copy_to_clipboard(text.into()));
+        }
+        DialogAnswer::Alternate => {
+            let picker = rfd::AsyncFileDialog::new()
+                .add_filter("Text", &["txt"])
+                .set_file_name(format!("{}-links.txt", filenamify(&pack_name)));
+            if let Some(file) = picker.save_file().await {
+                if let Err(e) = fs::write(file.path(), text).await {
+                    open_error_window(e.to_string());
+                }
             }
-            Ok(())
         }
-        Err(e) => Err(e.into()),
+        DialogAnswer::Cancel => {}
+    }
+}
+
+/// Writes a pack to a file picked by the user, the paths of its mods are left out since they only
+/// make sense on this machine
+async fn export_modpack(pack_name: String) {
+    let pack = match MOD_PACKS.try_lock() {
+        Ok(mut packs) => match loaded_pack(&mut packs, &pack_name) {
+            Some(pack) => pack.clone(),
+            None => return,
+        },
+        Err(_) => return,
+    };
+    let installed = get_mods();
+    let mods = pack
+        .mods
+        .iter()
+        .map(|m| ExportedPackMod {
+            name: m.name.clone(),
+            folder: m.folder_name(),
+            enabled: m.enabled,
+            source: installed
+                .iter()
+                .find(|d| *m == **d)
+                .and_then(get_provenance)
+                .map(|p| SharedLoadoutMod {
+                    id: p.gb_id,
+                    file: p.file_id,
+                }),
+        })
+        .collect();
+    let exported = ExportedPack {
+        name: pack.name,
+        mods,
+    };
+    let text = match sonic_rs::to_string_pretty(&exported) {
+        Ok(text) => text,
+        Err(e) => {
+            open_error_window(e.to_string());
+            return;
+        }
+    };
+    let picker = rfd::AsyncFileDialog::new()
+        .add_filter("Modpack", &["json"])
+        .set_file_name(format!("{}.json", filenamify(&pack_name)));
+    if let Some(file) = picker.save_file().await {
+        if let Err(e) = fs::write(file.path(), text).await {
+            open_error_window(e.to_string());
+        }
+    }
+}
+
+/// Gives a pack a new name and moves it to the file of that name, the applied pack is kept applied
+/// under its new name. Returns the new name
+fn rename_modpack(old: &str, new: &str) -> std::io::Result<String> {
+    pack_file_name(new)?;
+    if new == old {
+        return Ok(new.to_owned());
+    }
+    let mut packs = MOD_PACKS
+        .try_lock()
+        .map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string()))?;
+    if new == "All Mods" || packs.contains_key(new) || pack_file_exists(new) {
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("A modpack named {new} already exists"),
+        ));
+    }
+    let Some(mut pack) = loaded_pack(&mut packs, old).cloned() else {
+        return Err(std::io::Error::new(
+            ErrorKind::NotFound,
+            format!("Modpack {old} does not exist"),
+        ));
+    };
+    pack.name = new.to_owned();
+    save_modpack_sync(pack.clone(), ExistingPack::Refuse)?;
+    std::fs::remove_file(get_modpacks_folder()?.join(pack_file_name(old)?))?;
+    update_pack_index(old, None)?;
+    packs.remove(old);
+    packs.insert(new.to_owned(), pack);
+    drop(packs);
+    if let Ok(mut cfg) = R4D_CFG.try_lock() {
+        if cfg.applied_pack == old {
+            cfg.applied_pack = new.to_owned();
+            if let Err(e) = write_config_sync(cfg.clone()) {
+                eprintln!("Unable to save the renamed pack as applied: {e}");
+            }
+        }
+    }
+    Ok(new.to_owned())
+}
+
+/// Fills the pack selector with every pack sorted by name after "All Mods" and selects the active pack
+pub fn set_pack_list(ui: &App) {
+    let mut names: Vec<SharedString> = match MOD_PACKS.try_lock() {
+        Ok(packs) => packs.keys().map(|p| p.into()).collect(),
+        Err(_) => return,
+    };
+    names.sort_by_key(|s| s.to_lowercase());
+    names.insert(0, "All Mods".into());
+    let active = ui.get_active_pack();
+    let idx = names.iter().position(|p| *p == active).unwrap_or(0);
+    ui.set_modpacks(ModelRc::new(VecModel::from(names)));
+    ui.set_current_pack_idx(idx as i32);
+}
+
+/// Reads a pack written by [`export_modpack`] and saves it as a new pack. Mods are matched to the
+/// installed ones by folder and then by name, the rest are added disabled and show up as missing.
+/// Missing mods that were downloaded from GameBanana can be downloaded right away, they take their
+/// placeholder's place once installed
+async fn import_modpack(weak: slint::Weak<App>) {
+    let picker = rfd::AsyncFileDialog::new().add_filter("Modpack", &["json"]);
+    let Some(file) = picker.pick_file().await else {
+        return;
+    };
+    let exported = match fs::read_to_string(file.path()).await {
+        Ok(text) => match sonic_rs::from_str::<ExportedPack>(&text) {
+            Ok(exported) => exported,
+            Err(e) => {
+                open_error_window(format!("{} isn't a modpack:\n{e}", file.file_name()));
+                return;
+            }
+        },
+        Err(e) => {
+            open_error_window(e.to_string());
+            return;
+        }
+    };
+    let pack_name = filenamify(&exported.name);
+    if let Err(e) = pack_file_name(&pack_name) {
+        open_error_window(e.to_string());
+        return;
+    }
+    let exists = MOD_PACKS
+        .try_lock()
+        .map_or(true, |packs| packs.contains_key(&pack_name));
+    if exists || pack_name == "All Mods" || pack_file_exists(&pack_name) {
+        open_error_window(format!(
+            "A modpack named {pack_name} already exists, rename or delete it before importing"
+        ));
+        return;
+    }
+    let Some(mods_dir) = current_mods_dir() else {
+        return;
+    };
+    let installed = get_mods();
+    let mut pack = ModPack::new(pack_name.clone());
+    let mut missing = vec![];
+    let mut sources = vec![];
+    for m in exported.mods {
+        let found = installed
+            .iter()
+            .find(|d| d.dir_name().is_some_and(|dir| dir == m.folder))
+            .or_else(|| {
+                installed
+                    .iter()
+                    .find(|d| d.config["name"].as_str() == Some(m.name.as_str()))
+            });
+        match found {
+            Some(found) => {
+                let mut packmod = ModPackMod::from(found.clone());
+                packmod.enabled = m.enabled;
+                pack.mods.push(packmod);
+            }
+            None => {
+                // kept where the mod would be installed so it's picked up once it is
+                let path = mods_dir.join(&m.folder).join("config.toml");
+                pack.mods.push(ModPackMod {
+                    name: m.name.clone(),
+                    enabled: false,
+                    path: path.to_string_lossy().to_string(),
+                });
+                if let Some(source) = m.source {
+                    sources.push((path.to_string_lossy().to_string(), m.enabled, source));
+                }
+                missing.push(m.name);
+            }
+        }
+    }
+    if let Err(e) = save_modpack(pack.clone(), ExistingPack::Refuse).await {
+        open_error_window(e.to_string());
+        return;
+    }
+    if let Ok(mut packs) = MOD_PACKS.lock() {
+        packs.insert(pack_name.clone(), pack);
+    }
+    let name = pack_name.clone();
+    let _ = weak.upgrade_in_event_loop(move |ui| {
+        let binding = ui.get_modpacks();
+        if let Some(packs) = binding.as_any().downcast_ref::<VecModel<SharedString>>() {
+            packs.push(name.into());
+        }
+    });
+    if missing.is_empty() {
+        return;
+    }
+    let mut msg = format!(
+        "These mods of {pack_name} aren't installed:\n{}",
+        missing.join("\n")
+    );
+    if sources.is_empty() {
+        open_error_window(msg);
+        return;
+    }
+    msg += &format!(
+        "\n\n{} of them can be downloaded from GameBanana, download them now?",
+        sources.len()
+    );
+    if !ask_confirmation("Missing Mods".to_owned(), msg).await {
+        return;
+    }
+    let loadout = SharedLoadout {
+        name: pack_name.clone(),
+        mods: sources
+            .iter()
+            .map(|(_, _, source)| source.clone())
+            .collect(),
+    };
+    if !download_loadout_mods(&loadout).await {
+        return;
+    }
+    let _ = set_mods_table(&get_mods(), weak.clone());
+    if let Err(e) = fill_placeholders(&pack_name, &sources) {
+        open_error_window(format!(
+            "Unable to add the downloaded mods to {pack_name}:
+{e}"
+        ));
+        return;
+    }
+    let _ = weak.upgrade_in_event_loop(move |ui| {
+        if ui.get_active_pack().to_string() == pack_name {
+            ui.global::<ModpackLogic>()
+                .invoke_change_modpack(pack_name.into());
+        }
+    });
+}
+
+/// Puts the mods that were downloaded for an imported pack in the place of their placeholders,
+/// enabled as they were in the exported pack. `placeholders` are the path the placeholder was
+/// given, whether the mod was enabled and where it was downloaded from
+fn fill_placeholders(
+    pack_name: &str,
+    placeholders: &[(String, bool, SharedLoadoutMod)],
+) -> std::io::Result<()> {
+    let installed = get_mods();
+    let mut packs = MOD_PACKS
+        .lock()
+        .map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string()))?;
+    let Some(pack) = loaded_pack(&mut packs, pack_name) else {
+        return Ok(());
+    };
+    for (path, enabled, source) in placeholders {
+        let Some(found) = installed
+            .iter()
+            .find(|m| get_provenance(m).is_some_and(|p| p.gb_id == source.id))
+        else {
+            continue;
+        };
+        let mut packmod = ModPackMod::from(found.clone());
+        packmod.enabled = *enabled;
+        replace_placeholder(&mut pack.mods, path, packmod);
+    }
+    save_modpack_sync(pack.clone(), ExistingPack::Overwrite)
+}
+
+/// Replaces the entry with the placeholder's `path`, the download can end up in a different folder
+/// than the one the pack was exported with so any other entry of the same mod is dropped
+fn replace_placeholder(mods: &mut Vec<ModPackMod>, path: &str, packmod: ModPackMod) {
+    mods.retain(|m| m.path == path || *m != packmod);
+    if let Some(slot) = mods.iter_mut().find(|m| m.path == path) {
+        *slot = packmod;
+    }
+}
+
+/// Shows the applied pack's mods that match the search term and quick filter, along with how many
+/// mods each quick filter would show. Conflicts are looked for the first time they're filtered by
+pub fn refresh_pack_mods(weak: slint::Weak<App>) {
+    let (term, status) = PACK_FILTER
+        .lock()
+        .map(|filter| filter.clone())
+        .unwrap_or_default();
+    let entries: Vec<PackEntry> = get_pack_entries_in_order()
+        .into_iter()
+        .filter(|entry| match entry {
+            PackEntry::Installed(m) => m.search(&term),
+            PackEntry::Missing(m) => fuzzy_score(&term, &m.name).is_some(),
+        })
+        .collect();
+    let mods: Vec<DivaMod> = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            PackEntry::Installed(m) => Some(m.clone()),
+            PackEntry::Missing(_) => None,
+        })
+        .collect();
+    let conflicts = conflicting_mods(&mods);
+    if status == 3 && conflicts.is_none() {
+        let weak = weak.clone();
+        scan_conflicts(move || refresh_pack_mods(weak));
     }
+    let enabled = |m: &DivaMod| m.config["enabled"].as_bool().unwrap_or(true);
+    let conflicting = |m: &DivaMod| {
+        conflicts
+            .as_ref()
+            .is_some_and(|c| m.dir_name().is_some_and(|d| c.contains(&d)))
+    };
+    let counts = vec![
+        mods.iter().filter(|m| enabled(m)).count() as i32,
+        mods.iter().filter(|m| !enabled(m)).count() as i32,
+        conflicts.as_ref().map_or(-1, |c| c.len() as i32),
+        (entries.len() - mods.len()) as i32,
+    ];
+    let shown: Vec<DivaModElement> = entries
+        .iter()
+        .filter(|entry| match (entry, status) {
+            (PackEntry::Installed(m), 1) => enabled(m),
+            (PackEntry::Installed(m), 2) => !enabled(m),
+            (PackEntry::Installed(m), 3) => conflicting(m),
+            (PackEntry::Installed(_), 4) => false,
+            (PackEntry::Missing(_), 0 | 4) => true,
+            (PackEntry::Missing(_), _) => false,
+            (PackEntry::Installed(_), _) => true,
+        })
+        .map(|entry| match entry {
+            PackEntry::Installed(m) => m.clone().into(),
+            PackEntry::Missing(m) => m.to_element(),
+        })
+        .collect();
+    let _ = weak.upgrade_in_event_loop(move |ui| {
+        ui.set_pack_mods(ModelRc::new(VecModel::from(shown)));
+        ui.set_pack_filter_counts(ModelRc::new(VecModel::from(counts)));
+    });
+}
+
+fn session_key(pack: &str) -> String {
+    match pack {
+        "All Mods" => String::new(),
+        pack => pack.to_owned(),
+    }
+}
+
+/// Names of the mods a pack enables, "All Mods" uses the global priority
+fn enabled_pack_mods(cfg: &DivaConfig, pack: &str) -> Vec<String> {
+    let mods = match MOD_PACKS.try_lock() {
+        Ok(mut packs) => match loaded_pack(&mut packs, pack) {
+            Some(p) => p.mods.clone(),
+            None => cfg.priority.clone(),
+        },
+        Err(_) => return vec![],
+    };
+    mods.into_iter()
+        .filter(|m| m.enabled)
+        .map(|m| m.name)
+        .collect()
+}
+
+/// Names of the mods a pack lists whose folder is gone
+fn missing_pack_mods(pack: &str) -> Vec<String> {
+    match MOD_PACKS.try_lock() {
+        Ok(mut packs) => match loaded_pack(&mut packs, pack) {
+            Some(p) => p
+                .mods
+                .iter()
+                .filter(|m| m.dir_name().is_none())
+                .map(|m| m.name.clone())
+                .collect(),
+            None => vec![],
+        },
+        Err(_) => vec![],
+    }
+}
+
+/// Whether two pack names point to the same pack, no pack and "All Mods" both use the global priority
+pub fn is_same_pack(left: &str, right: &str) -> bool {
+    session_key(left) == session_key(right)
+}
+
+/// Remembers the load order before the first reorder of the session, later calls do nothing
+pub fn snapshot_order(pack: &str, order: &Vec<ModPackMod>) {
+    if let Ok(mut orders) = SESSION_ORDERS.lock() {
+        orders
+            .entry(session_key(pack))
+            .or_insert_with(|| order.clone());
+    }
+}
+
+/// The applied pack's load order, the global priority is used when no pack is applied
+fn current_order() -> Option<(String, Vec<ModPackMod>)> {
+    let cfg = R4D_CFG.try_lock().ok()?;
+    let key = session_key(&cfg.applied_pack);
+    if key.is_empty() {
+        return Some((key, cfg.priority.clone()));
+    }
+    let mut packs = MOD_PACKS.try_lock().ok()?;
+    Some((
+        key,
+        loaded_pack(&mut packs, &cfg.applied_pack)?.mods.clone(),
+    ))
+}
+
+/// Finds the mods that were moved to get from one load order to the other along with their old
+/// and new positions, mods that keep their place relative to each other aren't counted as moved
+fn moved_mods(before: &Vec<ModPackMod>, after: &Vec<ModPackMod>) -> Vec<(String, usize, usize)> {
+    let before: Vec<(usize, &ModPackMod)> = before
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| after.contains(m))
+        .collect();
+    let after: Vec<(usize, &ModPackMod)> = after
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| before.iter().any(|(_, b)| b == m))
+        .collect();
+    // longest common subsequence, everything outside of it was moved
+    let mut lcs = vec![vec![0; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            lcs[i][j] = if before[i].1 == after[j].1 {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut kept = vec![false; after.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() && j < after.len() {
+        if before[i].1 == after[j].1 {
+            kept[j] = true;
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    after
+        .iter()
+        .zip(kept)
+        .filter(|(_, kept)| !kept)
+        .filter_map(|((new, m), _)| {
+            let (old, _) = before.iter().find(|(_, b)| b == m)?;
+            Some((m.name.clone(), *old, *new))
+        })
+        .collect()
+}
+
+/// The mods moved in the applied pack since the start of the session
+fn order_changes() -> Option<(String, Vec<(String, usize, usize)>)> {
+    let (key, order) = current_order()?;
+    let orders = SESSION_ORDERS.lock().ok()?;
+    let start = orders.get(&key)?;
+    Some((key, moved_mods(start, &order)))
+}
+
+pub fn refresh_order_changes(weak: slint::Weak<App>) {
+    let moved = order_changes().map_or(0, |(_, moved)| moved.len() as i32);
+    let _ = weak.upgrade_in_event_loop(move |ui| ui.set_order_changes(moved));
+}
+
+/// Lists the mods moved since the start of the session and offers to put them all back
+async fn show_order_changes(weak: slint::Weak<App>) {
+    let Some((key, moved)) = order_changes() else {
+        return;
+    };
+    if moved.is_empty() {
+        return;
+    }
+    let lines: Vec<String> = moved
+        .iter()
+        .map(|(name, old, new)| format!("{name}: #{} → #{}", old + 1, new + 1))
+        .collect();
+    let answer = ask_choice(
+        "Load Order Changes".to_owned(),
+        format!(
+            "{} mods were moved this session:\n{}",
+            moved.len(),
+            lines.join("\n")
+        ),
+        Some("Undo All".to_owned()),
+        None,
+    )
+    .await;
+    if answer != DialogAnswer::Confirm {
+        return;
+    }
+    let Some(start) = SESSION_ORDERS
+        .lock()
+        .ok()
+        .and_then(|mut orders| orders.remove(&key))
+    else {
+        return;
+    };
+    let Some((_, mut order)) = current_order() else {
+        return;
+    };
+    // only the order is restored, mods keep their current enabled state and new mods go last
+    order.sort_by_key(|m| start.iter().position(|s| s == m).unwrap_or(usize::MAX));
+    let res = if key.is_empty() {
+        let cfg = match R4D_CFG.try_lock() {
+            Ok(mut cfg) => {
+                cfg.priority = order;
+                cfg.clone()
+            }
+            Err(e) => return open_error_window(e.to_string()),
+        };
+        write_config(cfg).await
+    } else {
+        let pack = match MOD_PACKS.try_lock() {
+            Ok(mut packs) => match loaded_pack(&mut packs, &key) {
+                Some(pack) => {
+                    pack.mods = order;
+                    pack.clone()
+                }
+                None => return,
+            },
+            Err(e) => return open_error_window(e.to_string()),
+        };
+        save_modpack(pack, ExistingPack::Overwrite).await
+    };
+    if let Err(e) = res {
+        open_error_window(format!("Unable to restore the load order: \n{e}"));
+        return;
+    }
+    let _ = weak.upgrade_in_event_loop(move |ui| {
+        let applied = if key.is_empty() {
+            "All Mods".into()
+        } else {
+            key.into()
+        };
+        ui.global::<ModpackLogic>().invoke_change_modpack(applied);
+    });
 }
 
 pub async fn apply_mod_priority() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if let Ok(cfg) = R4D_CFG.try_lock() {
         let mut prio = vec![];
         if cfg.applied_pack != "".to_owned() {
-            if let Ok(packs) = MOD_PACKS.try_lock() {
-                if let Some(current_pack) = packs.get(&cfg.applied_pack) {
+            if let Ok(mut packs) = MOD_PACKS.try_lock() {
+                if let Some(current_pack) = loaded_pack(&mut packs, &cfg.applied_pack) {
                     for m in current_pack.clone().mods {
                         prio.push(m.clone().dir_name().unwrap_or(m.name));
                     }
@@ -582,3 +1625,157 @@ pub fn hash_dir_name(dir: String) -> String {
     let hash = Sha256::digest(dir);
     Base64::encode_string(&hash)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_dir;
+
+    /// Creates a mod folder with a config.toml and returns the mod and its pack entry
+    fn test_mod(mods_dir: &std::path::Path, folder: &str, name: &str) -> (DivaMod, ModPackMod) {
+        let dir = mods_dir.join(folder);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = format!("enabled = true\nname = \"{name}\"\n");
+        let path = dir.join("config.toml");
+        std::fs::write(&path, &config).unwrap();
+        let path = path.display().to_string();
+        let module = DivaMod {
+            path: path.clone(),
+            config: config.parse().unwrap(),
+            dependencies: vec![],
+        };
+        let entry = ModPackMod {
+            name: name.to_owned(),
+            enabled: true,
+            path,
+        };
+        (module, entry)
+    }
+
+    #[test]
+    fn toggling_keeps_pack_membership_and_order() {
+        let dir = test_dir("toggle");
+        let (_, first) = test_mod(&dir, "first", "First");
+        let (second_mod, second) = test_mod(&dir, "second", "Second");
+        let (_, third) = test_mod(&dir, "third", "Third");
+        let mut mods = vec![first, second, third];
+        let order = |mods: &Vec<ModPackMod>| -> Vec<String> {
+            mods.iter().map(|m| m.name.clone()).collect()
+        };
+        let before = order(&mods);
+
+        assert!(set_mod_enabled(&mut mods, &second_mod, false));
+        assert_eq!(order(&mods), before);
+        assert_eq!(
+            mods.iter().map(|m| m.enabled).collect::<Vec<_>>(),
+            [true, false, true]
+        );
+
+        assert!(set_mod_enabled(&mut mods, &second_mod, true));
+        assert_eq!(order(&mods), before);
+        assert!(mods.iter().all(|m| m.enabled));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn downloaded_mods_take_their_placeholders_place() {
+        let dir = test_dir("placeholder");
+        let (_, first) = test_mod(&dir, "first", "First");
+        let (_, last) = test_mod(&dir, "last", "Last");
+        let placeholder = ModPackMod {
+            name: "Cool Mod".to_owned(),
+            enabled: false,
+            path: dir
+                .join("Cool Mod")
+                .join("config.toml")
+                .display()
+                .to_string(),
+        };
+        // installed under a different folder and already added to the end of the pack
+        let (_, mut downloaded) = test_mod(&dir, "Cool Mod v2", "Cool Mod");
+        downloaded.enabled = false;
+        let mut mods = vec![first, placeholder.clone(), last, downloaded.clone()];
+
+        downloaded.enabled = true;
+        replace_placeholder(&mut mods, &placeholder.path, downloaded.clone());
+        let names: Vec<Option<String>> = mods.iter().map(|m| m.dir_name()).collect();
+        assert_eq!(
+            names,
+            [
+                Some("first".to_owned()),
+                Some("Cool Mod v2".to_owned()),
+                Some("last".to_owned())
+            ]
+        );
+        assert!(mods[1].enabled);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn toggling_matches_by_folder() {
+        let dir = test_dir("toggle-same-name");
+        let (_, first) = test_mod(&dir, "first", "Same Name");
+        let (second_mod, second) = test_mod(&dir, "second", "Same Name");
+        let (outside, _) = test_mod(&dir, "outside", "Outside");
+        let mut mods = vec![first, second];
+
+        assert!(set_mod_enabled(&mut mods, &second_mod, false));
+        assert!(mods[0].enabled);
+        assert!(!mods[1].enabled);
+        // a mod that isn't in the pack isn't added to it
+        assert!(!set_mod_enabled(&mut mods, &outside, false));
+        assert_eq!(mods.len(), 2);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn pack_names_that_are_empty_as_files_are_refused() {
+        for name in ["", " ", "   ", "\u{3000}"] {
+            let err = pack_file_name(name).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn pack_names_become_file_names() {
+        assert_eq!(pack_file_name("My Pack").unwrap(), "My Pack.json");
+        assert_eq!(pack_file_name("a/b").unwrap(), "a_b.json");
+        assert_eq!(pack_file_name("..").unwrap(), "_.json");
+        // control characters are replaced, so they still leave a usable name
+        assert_eq!(pack_file_name("\t").unwrap(), "_.json");
+    }
+
+    #[test]
+    fn existing_pack_files_are_only_replaced_when_asked() {
+        let dir = test_dir("existing-pack");
+        let file = dir.join("Pack.json");
+        assert!(check_existing_pack(&file, ExistingPack::Refuse).is_ok());
+        assert!(check_existing_pack(&file, ExistingPack::Overwrite).is_ok());
+
+        std::fs::write(&file, "{}").unwrap();
+        let err = check_existing_pack(&file, ExistingPack::Refuse).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        assert!(check_existing_pack(&file, ExistingPack::Overwrite).is_ok());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn packs_listed_from_the_index_are_read_before_saving() {
+        let mut pack = ModPack::new("Pack".to_owned());
+        assert!(check_pack_read(&pack).is_ok());
+        pack.unread_file = Some("Pack.json".to_owned());
+        assert!(check_pack_read(&pack).is_err());
+    }
+
+    #[test]
+    fn only_whole_pack_files_are_listed() {
+        let dir = test_dir("pack-files");
+        std::fs::write(dir.join("Pack.json"), "{}").unwrap();
+        std::fs::write(dir.join("Pack.json.tmp"), "{").unwrap();
+        std::fs::create_dir(dir.join("folder")).unwrap();
+        assert!(is_pack_file(&dir.join("Pack.json")));
+        assert!(!is_pack_file(&dir.join("Pack.json.tmp")));
+        assert!(!is_pack_file(&dir.join("folder")));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,5 +1,8 @@
 use std::error::Error;
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
 
 use interprocess::local_socket::{ListenerOptions, NameType, ToFsName, ToNsName};
 use interprocess::local_socket::{
@@ -7,14 +10,38 @@ use interprocess::local_socket::{
     GenericNamespaced, tokio::{prelude::*, Stream},
 };
 use slint::Weak;
+use serde::Serialize;
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
     try_join,
 };
+use tokio::net::TcpListener;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::{oneshot, Notify};
 
+use crate::diva::{ask_choice, open_error_window};
+use crate::gamebanana::{fetch_mod, get_active_downloads, search_gb, DownloadStatus};
 use crate::App;
 
+static STATUS_SERVER_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Kept in step with `status_server` so the server doesn't have to wait for the config
+static STATUS_SERVER_ENABLED: AtomicBool = AtomicBool::new(false);
+/// Wakes the server up to check whether it was disabled
+static STATUS_SERVER_STOP: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Urls starting with this are checked by the one-click handler instead of being opened
+pub const ONECLICK_TEST_PREFIX: &str = "r4d-oneclick-test:";
+/// How long the handler gets to parse and fetch a test url before the test counts as failed
+const ONECLICK_TEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Gets the handler's result for the test url that is currently on its way
+static ONECLICK_TEST: Mutex<Option<oneshot::Sender<Result<String, String>>>> = Mutex::new(None);
+
+#[derive(Serialize)]
+struct StatusResponse {
+    active: usize,
+    downloads: Vec<DownloadStatus>,
+}
+
 /// This is the function for the url handling, should this return Result(True) we know that we are
 /// the listening server and should run the display window
 pub async fn spawn_listener(dmm_url_tx: Sender<String>, _weak: Weak<App>) -> Result<bool, Box<dyn Error>> {
@@ -110,7 +137,7 @@ is in use by another process and try again."
 }
 
 
-pub async fn try_send_mmdl(dmm: String) -> Result<(), Box<dyn Error>> {
+pub async fn try_send_mmdl(dmm: String) -> Result<(), Box<dyn Error + Send + Sync>> {
     let printname = "rust4diva.sock";
     // Pick a name.
     let name = if GenericNamespaced::is_supported() {
@@ -156,4 +183,151 @@ pub async fn try_send_mmdl(dmm: String) -> Result<(), Box<dyn Error>> {
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Sends a one-click url for a mod on GameBanana through the listener the same way a browser would
+/// and shows which stage of the pipeline failed, if any. The handler only checks the url and
+/// doesn't open or download anything
+pub async fn test_oneclick() {
+    let url = match oneclick_test_url().await {
+        Ok(url) => url,
+        Err(e) => {
+            open_error_window(format!(
+                "One-click test failed while looking for a mod to test with:\n{e}"
+            ));
+            return;
+        }
+    };
+    let (tx, rx) = oneshot::channel();
+    if let Ok(mut test) = ONECLICK_TEST.lock() {
+        *test = Some(tx);
+    }
+    if let Err(e) = try_send_mmdl(format!("{ONECLICK_TEST_PREFIX}{url}")).await {
+        open_error_window(format!(
+            "One-click test failed at the listener, {url} could not be sent to it:\n{e}"
+        ));
+        return;
+    }
+    let msg = match tokio::time::timeout(ONECLICK_TEST_TIMEOUT, rx).await {
+        Ok(Ok(Ok(found))) => found,
+        Ok(Ok(Err(e))) => {
+            open_error_window(format!("One-click test failed {e}"));
+            return;
+        }
+        _ => {
+            open_error_window(format!(
+                "One-click test failed at the handler, the listener got {url} but the handler never answered"
+            ));
+            return;
+        }
+    };
+    let _ = ask_choice(
+        "One-Click Test Passed".to_owned(),
+        format!("{url} was received, parsed, fetched and would have been queued:\n{msg}"),
+        Some("OK".to_owned()),
+        None,
+    )
+    .await;
+}
+
+/// Builds a one-click url for the first file of a popular mod
+async fn oneclick_test_url() -> Result<String, Box<dyn Error + Send + Sync>> {
+    let results = search_gb("".to_owned(), 1, 1, "Mod", 10, |_| {}).await?;
+    let item = results
+        .records
+        .iter()
+        .find(|r| r.has_files && r.model_name == "Mod")
+        .ok_or("GameBanana didn't return any mods with files")?;
+    let id = item.id as i32;
+    let file = fetch_mod(id)
+        .await?
+        .files
+        .first()
+        .map(|f| f.id)
+        .ok_or(format!("{} has no files", item.name))?;
+    Ok(format!(
+        "divamodmanager:https://gamebanana.com/mmdl/{file},Mod,{id}"
+    ))
+}
+
+/// Hands the one-click handler's result for a test url to [`test_oneclick`]
+pub fn finish_oneclick_test(result: Result<String, String>) {
+    if let Some(tx) = ONECLICK_TEST.lock().ok().and_then(|mut test| test.take()) {
+        let _ = tx.send(result);
+    }
+}
+
+/// Serves the running downloads as json on localhost so stream overlays and other tools can show
+/// them, this is only started when `status_server` is enabled and is shut down with
+/// [`stop_status_server`]. No CORS header is sent, so websites open in a browser can't read the list
+pub async fn spawn_status_server(port: u16) -> Result<(), Box<dyn Error + Send + Sync>> {
+    STATUS_SERVER_ENABLED.store(true, Ordering::SeqCst);
+    if STATUS_SERVER_RUNNING.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    // bound to localhost only, nothing outside of this machine should see it
+    let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            STATUS_SERVER_RUNNING.store(false, Ordering::SeqCst);
+            return Err(e.into());
+        }
+    };
+    println!("Download status available at http://127.0.0.1:{port}");
+
+    tokio::spawn(async move {
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = STATUS_SERVER_STOP.notified() => {
+                    // the server may have been enabled again before this woke up
+                    if STATUS_SERVER_ENABLED.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    break;
+                }
+            };
+            let (mut conn, _) = match accepted {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("There was an error with an incoming status connection: {e}");
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                // every path gets the same answer so the request itself is only read to be polite
+                let mut request = [0u8; 1024];
+                let _ = conn.read(&mut request).await;
+                let downloads = get_active_downloads();
+                let status = StatusResponse {
+                    active: downloads.len(),
+                    downloads,
+                };
+                let body = match sonic_rs::to_string(&status) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        return;
+                    }
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                if let Err(e) = conn.write_all(response.as_bytes()).await {
+                    eprintln!("Error while answering status request: {e}");
+                }
+            });
+        }
+        // the listener is dropped here so the port is free again
+        STATUS_SERVER_RUNNING.store(false, Ordering::SeqCst);
+        println!("Download status server stopped");
+    });
+    Ok(())
+}
+
+/// Shuts the download status server down if it's running
+pub fn stop_status_server() {
+    STATUS_SERVER_ENABLED.store(false, Ordering::SeqCst);
+    STATUS_SERVER_STOP.notify_one();
+}
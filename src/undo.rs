@@ -0,0 +1,128 @@
+//! In-session undo and redo for changes made to the mod list, each action keeps what's needed to
+//! reverse it and is replayed through the same callbacks the ui uses
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use slint::ComponentHandle;
+
+use crate::diva::open_error_window;
+use crate::modmanagement::{get_mods, invalidate_mod_size, load_mods, move_dir, set_mods_table};
+use crate::modpacks::is_same_pack;
+use crate::slint_generatedApp::App;
+use crate::{DivaModElement, ModLogic, ModpackLogic, MODS, MOD_PACKS, R4D_CFG};
+
+const MAX_HISTORY: usize = 100;
+
+#[derive(Clone, Debug)]
+pub enum ModAction {
+    /// the enabled flag of the mod in this folder was flipped
+    Toggle(String),
+    /// a mod was moved from `old` to `new` in the load order of `pack`
+    Reorder { pack: String, old: i32, new: i32 },
+    /// the applied pack was changed
+    ApplyPack { from: String, to: String },
+    /// a mod folder was deleted by moving it to the trash
+    Delete { dir: PathBuf, trash: PathBuf },
+}
+
+static UNDO: Mutex<Vec<ModAction>> = Mutex::new(vec![]);
+static REDO: Mutex<Vec<ModAction>> = Mutex::new(vec![]);
+/// set while an action is replayed so the callbacks it goes through don't record it again
+static REPLAYING: AtomicBool = AtomicBool::new(false);
+
+/// Adds an action to the undo stack, anything that was undone can't be redone after this
+pub fn record_action(action: ModAction) {
+    if REPLAYING.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(mut undo) = UNDO.lock() {
+        undo.push(action);
+        if undo.len() > MAX_HISTORY {
+            undo.remove(0);
+        }
+    }
+    if let Ok(mut redo) = REDO.lock() {
+        redo.clear();
+    }
+}
+
+pub fn init(ui: &App) {
+    let weak = ui.as_weak();
+    ui.global::<ModLogic>().on_undo(move || {
+        step(&weak.unwrap(), &UNDO, &REDO, true);
+    });
+    let weak = ui.as_weak();
+    ui.global::<ModLogic>().on_redo(move || {
+        step(&weak.unwrap(), &REDO, &UNDO, false);
+    });
+}
+
+/// Takes the last action off one stack, replays it and moves it to the other stack if it worked
+fn step(ui: &App, from: &Mutex<Vec<ModAction>>, to: &Mutex<Vec<ModAction>>, reverse: bool) {
+    let Some(action) = from.lock().ok().and_then(|mut actions| actions.pop()) else {
+        return;
+    };
+    REPLAYING.store(true, Ordering::Relaxed);
+    let res = replay(ui, &action, reverse);
+    REPLAYING.store(false, Ordering::Relaxed);
+    match res {
+        Ok(_) => {
+            if let Ok(mut actions) = to.lock() {
+                actions.push(action);
+            }
+        }
+        Err(e) => open_error_window(e),
+    }
+}
+
+fn replay(ui: &App, action: &ModAction, reverse: bool) -> Result<(), String> {
+    match action {
+        ModAction::Toggle(dir) => {
+            let module = MODS
+                .try_lock()
+                .map_err(|e| e.to_string())?
+                .get(dir)
+                .cloned()
+                .ok_or(format!("{dir} is no longer installed"))?;
+            ui.global::<ModLogic>()
+                .invoke_toggle_mod(DivaModElement::from(module));
+        }
+        ModAction::Reorder { pack, old, new } => {
+            let applied = R4D_CFG
+                .try_lock()
+                .map_err(|e| e.to_string())?
+                .applied_pack
+                .clone();
+            if !is_same_pack(&applied, pack) {
+                return Err(format!("Apply {pack} again to change its load order"));
+            }
+            let (old, new) = if reverse { (*new, *old) } else { (*old, *new) };
+            ui.global::<ModLogic>().invoke_set_priority(old, new);
+        }
+        ModAction::ApplyPack { from, to } => {
+            let pack = if reverse { from } else { to };
+            let exists = MOD_PACKS
+                .try_lock()
+                .map_err(|e| e.to_string())?
+                .contains_key(pack);
+            if !exists && !is_same_pack(pack, "") {
+                return Err(format!("The modpack {pack} no longer exists"));
+            }
+            let pack = if pack.is_empty() { "All Mods" } else { pack };
+            ui.global::<ModpackLogic>()
+                .invoke_change_modpack(pack.into());
+        }
+        ModAction::Delete { dir, trash } => {
+            let (src, dst) = if reverse { (trash, dir) } else { (dir, trash) };
+            move_dir(src, dst).map_err(|e| e.to_string())?;
+            if let Some(name) = dir.file_name() {
+                invalidate_mod_size(&name.to_string_lossy());
+            }
+            load_mods().map_err(|e| e.to_string())?;
+            set_mods_table(&get_mods(), ui.as_weak()).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
@@ -1,3 +1,6 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
 use reqwest::{Client, ClientBuilder};
 
 pub fn reqwest_client() -> Client {
@@ -10,3 +13,180 @@ pub fn reqwest_client() -> Client {
         .build()
         .expect("Something went horrible wrong when constructing our reqwest client")
 }
+
+/// Opens a link with the system's default handler, every window's HyperLink global goes through this
+pub fn open_hyperlink(link: &str) {
+    if let Err(e) = open::that(link) {
+        eprintln!("{e}");
+    }
+}
+
+/// Finds an existing path that only differs from `path` in casing, each part that doesn't exist is
+/// looked up in its parent ignoring case. Returns None if any part can't be found
+pub fn resolve_path_casing(path: &Path) -> Option<PathBuf> {
+    if path.exists() {
+        return Some(path.to_path_buf());
+    }
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        let next = resolved.join(component);
+        if next.exists() {
+            resolved = next;
+            continue;
+        }
+        let wanted = component.as_os_str().to_string_lossy().to_lowercase();
+        let parent = match resolved.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => resolved.as_path(),
+        };
+        let found = fs::read_dir(parent)
+            .ok()?
+            .flatten()
+            .find(|e| e.file_name().to_string_lossy().to_lowercase() == wanted)?;
+        resolved = resolved.join(found.file_name());
+    }
+    Some(resolved)
+}
+
+/// A file found by [`walk_files`], `relative` is its path from the walked folder using `/`
+pub struct WalkedFile {
+    pub path: PathBuf,
+    pub relative: String,
+    pub size: u64,
+}
+
+/// Everything [`walk_files`] could read, `skipped` counts the entries it had to leave out
+pub struct DirWalk {
+    pub files: Vec<WalkedFile>,
+    pub skipped: usize,
+}
+
+/// Lists every file under `dir`. Entries that can't be read, like folders without permission, are
+/// logged and skipped so one bad entry doesn't stop the rest of the walk. Symlinks are never
+/// followed, they are listed as files so linked folders can't be walked in circles
+pub fn walk_files(dir: &Path) -> DirWalk {
+    let mut walk = DirWalk {
+        files: vec![],
+        skipped: 0,
+    };
+    let mut dirs = vec![(dir.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Skipping {}: {e}", dir.display());
+                walk.skipped += 1;
+                continue;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!("Skipping an entry of {}: {e}", dir.display());
+                    walk.skipped += 1;
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let relative = format!("{prefix}{}", entry.file_name().to_string_lossy());
+            match path.symlink_metadata() {
+                Ok(meta) if meta.is_dir() => dirs.push((path, format!("{relative}/"))),
+                Ok(meta) => walk.files.push(WalkedFile {
+                    path,
+                    relative,
+                    size: meta.len(),
+                }),
+                Err(e) => {
+                    eprintln!("Skipping {}: {e}", path.display());
+                    walk.skipped += 1;
+                }
+            }
+        }
+    }
+    walk
+}
+
+/// Scores how well `query` matches `text`, higher is better and None means it doesn't match.
+///
+/// Substring matches always rank first, otherwise the query's characters have to show up in
+/// order like fzf, with bonuses for consecutive characters and the start of words. Queries of 4
+/// or more characters may have one character that isn't found so small typos still match
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let text = text.to_lowercase();
+    if let Some(pos) = text.find(&query) {
+        return Some(10_000 - pos.min(1000) as i32);
+    }
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = text.chars().collect();
+    let mut misses = if query.len() >= 4 { 1 } else { 0 };
+    let mut score = 0;
+    let mut start = 0;
+    for q in query {
+        let Some(gap) = text[start..].iter().position(|c| *c == q) else {
+            if misses == 0 {
+                return None;
+            }
+            misses -= 1;
+            score -= 20;
+            continue;
+        };
+        let idx = start + gap;
+        score += 10;
+        if gap == 0 && idx > 0 {
+            score += 15;
+        }
+        if idx == 0 || !text[idx - 1].is_alphanumeric() {
+            score += 10;
+        }
+        score -= gap.min(10) as i32;
+        start = idx + 1;
+    }
+    Some(score.max(1))
+}
+
+/// An empty folder in the system's temp folder for a single test
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("r4d-test-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substring_matches_rank_first() {
+        let score = |query, text| fuzzy_score(query, text).unwrap();
+        assert_eq!(score("MIKU", "miku"), 10_000);
+        assert!(score("miku", "miku expansion") > score("miku", "hatsune miku"));
+        assert!(score("ik", "miku") > score("mku", "miku"));
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn word_starts_rank_higher() {
+        assert!(fuzzy_score("hm", "hatsune miku").unwrap() > fuzzy_score("hm", "chasm").unwrap());
+    }
+
+    #[test]
+    fn long_queries_allow_one_typo() {
+        assert!(fuzzy_score("mikv", "miku").is_some());
+        assert!(fuzzy_score("mkv", "miku").is_none());
+        assert!(fuzzy_score("mxxu", "miku").is_none());
+    }
+
+    #[test]
+    fn non_matches() {
+        assert_eq!(fuzzy_score("abc", "xyz"), None);
+        // the characters have to show up in order
+        assert_eq!(fuzzy_score("ukim", "miku"), None);
+    }
+}